@@ -0,0 +1,162 @@
+//! The `extract` module contains the `FromRequest` trait and the extractors that the arguments of an `#[action]` can use.
+//!
+//! An action defined with the [`action`](crate::action) attribute takes any number of arguments whose types implement
+//! `FromRequest`. They are extracted from the `Res` and the `Req` of the event, in order, before the body of the action
+//! runs. If an extraction fails, the action is not run and the error goes to the error handler of the `App`.
+//!
+//! # Extractors
+//!
+//! * `Res` and `Req`: The response and the request of the action.
+//! * `Text`: The text sent by the user.
+//! * `Json<T>`: The `Data` of the request, deserialized into `T`.
+//! * `Page`: The page of the `Data`, used to paginate the responses.
+//! * `Session`: The session of the user.
+//! * `Session<T>`: The [`SessionValue`] `T` stored in the session of the user, or `T::default()`.
+//! * `Attachments`: The attachments sent by the user.
+//! * `Params`: The parameters captured by the route pattern.
+//! * `Arc<Query>`: The database of the application.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use russenger::prelude::*;
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize)]
+//! struct Order {
+//!     product: String,
+//!     quantity: u32,
+//! }
+//!
+//! #[action]
+//! async fn order(res: Res, req: Req, Json(order): Json<Order>, session: Session) -> Result<()> {
+//!     session.set("last_product", &order.product).await?;
+//!     let message = format!("{} x {} ordered", order.quantity, order.product);
+//!     res.send(TextModel::new(&req.user, message)).await?;
+//!     Ok(())
+//! }
+//!
+//! #[action]
+//! async fn name(res: Res, req: Req, Text(name): Text) -> Result<()> {
+//!     res.send(TextModel::new(&req.user, format!("Hello {name}"))).await?;
+//!     Ok(())
+//! }
+//!
+//! let router = router![("/order", order), ("/name", name)];
+//! ```
+use std::{future::Future, sync::Arc};
+
+use serde::de::DeserializeOwned;
+
+use crate::core::{request::Req, response::Res, router::Params};
+use crate::db::{Query, Session, SessionValue};
+use crate::error::Result;
+use crate::response_models::data::Page;
+use crate::services::Attachment;
+
+/// `FromRequest` is implemented by the types that can be extracted from the `Res` and the `Req` of an action.
+///
+/// # Examples
+///
+/// Extracting the language of the user from their session:
+///
+/// ```rust
+/// use std::future::Future;
+/// use russenger::core::extract::FromRequest;
+/// use russenger::prelude::*;
+///
+/// struct Language(String);
+///
+/// impl FromRequest for Language {
+///     fn from_request(_: &Res, req: &Req) -> impl Future<Output = Result<Self>> + Send {
+///         let session = req.session();
+///         async move {
+///             let language = session.get("language").await?.unwrap_or_else(|| "en".to_owned());
+///             Ok(Language(language))
+///         }
+///     }
+/// }
+///
+/// #[action]
+/// async fn index(res: Res, req: Req, Language(language): Language) -> Result<()> {
+///     let message = if language == "fr" { "Bonjour" } else { "Hello" };
+///     res.send(TextModel::new(&req.user, message)).await?;
+///     Ok(())
+/// }
+/// ```
+pub trait FromRequest: Sized {
+    /// Extracts the value from the response and the request of the action.
+    fn from_request(res: &Res, req: &Req) -> impl Future<Output = Result<Self>> + Send;
+}
+
+/// The text sent by the user.
+#[derive(Debug, Clone)]
+pub struct Text(pub String);
+
+/// The `Data` of the request, deserialized into `T`.
+#[derive(Debug, Clone)]
+pub struct Json<T>(pub T);
+
+/// The attachments sent by the user with their message.
+#[derive(Debug, Clone)]
+pub struct Attachments(pub Vec<Attachment>);
+
+impl FromRequest for Res {
+    async fn from_request(res: &Res, _: &Req) -> Result<Self> {
+        Ok(res.clone())
+    }
+}
+
+impl FromRequest for Req {
+    async fn from_request(_: &Res, req: &Req) -> Result<Self> {
+        Ok(req.clone())
+    }
+}
+
+impl FromRequest for Text {
+    async fn from_request(_: &Res, req: &Req) -> Result<Self> {
+        Ok(Text(req.data.get_value()?))
+    }
+}
+
+impl<T: DeserializeOwned + Send> FromRequest for Json<T> {
+    async fn from_request(_: &Res, req: &Req) -> Result<Self> {
+        Ok(Json(req.data.get_value()?))
+    }
+}
+
+impl FromRequest for Page {
+    async fn from_request(_: &Res, req: &Req) -> Result<Self> {
+        Ok(req.data.get_page().unwrap_or_default())
+    }
+}
+
+impl FromRequest for Session {
+    async fn from_request(_: &Res, req: &Req) -> Result<Self> {
+        Ok(req.session())
+    }
+}
+
+impl<T: SessionValue> FromRequest for Session<T> {
+    async fn from_request(_: &Res, req: &Req) -> Result<Self> {
+        req.session().load().await
+    }
+}
+
+impl FromRequest for Attachments {
+    async fn from_request(_: &Res, req: &Req) -> Result<Self> {
+        Ok(Attachments(req.attachments.clone()))
+    }
+}
+
+impl FromRequest for Params {
+    async fn from_request(_: &Res, req: &Req) -> Result<Self> {
+        Ok(req.params.clone())
+    }
+}
+
+impl FromRequest for Arc<Query> {
+    async fn from_request(_: &Res, req: &Req) -> Result<Self> {
+        Ok(req.query.clone())
+    }
+}
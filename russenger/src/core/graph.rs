@@ -0,0 +1,615 @@
+//! The `graph` module contains the `GraphClient` used by `Res::send` to call the Facebook Graph API, and the types it returns.
+//!
+//! A successful call returns a `SendResponse` with the ids of the recipient and of the message. A failed
+//! call returns a `GraphError`, which parses the error object of the Graph API and classifies it.
+//!
+//! # Examples
+//!
+//! Handling the user blocking the page:
+//!
+//! ```rust
+//! use russenger::prelude::*;
+//!
+//! async fn index(res: Res, req: Req) -> Result<()> {
+//!     match res.send(TextModel::new(&req.user, "Hello")).await {
+//!         Ok(response) => println!("Message sent: {:?}", response.message_id),
+//!         Err(GraphError::RecipientUnavailable(_)) => println!("{} is not available", req.user),
+//!         Err(err) => return Err(err.into()),
+//!     }
+//!
+//!     Ok(())
+//! }
+//! ```
+//!
+//! ## Reference
+//!
+//! [Facebook Messenger Platform - Error Codes](https://developers.facebook.com/docs/messenger-platform/error-codes)
+use std::{
+    fmt,
+    time::{Duration, Instant, SystemTime},
+};
+
+use reqwest::header::RETRY_AFTER;
+use serde::{Deserialize, Serialize};
+use tracing::{field::Empty, info_span, Instrument};
+
+use crate::metrics;
+
+/// The default base URL of the Graph API.
+pub const DEFAULT_BASE_URL: &str = "https://graph.facebook.com";
+
+/// The default timeout of a request to the Graph API.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The body of a successful call to the Send API.
+///
+/// `recipient_id` and `message_id` are absent for the endpoints that do not send a message,
+/// like the messenger profile which only returns a `result`.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct SendResponse {
+    pub recipient_id: Option<String>,
+    pub message_id: Option<String>,
+    pub result: Option<String>,
+}
+
+/// The error object returned by the Graph API.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct GraphErrorBody {
+    #[serde(default)]
+    pub message: String,
+    #[serde(rename = "type", default)]
+    pub r#type: String,
+    #[serde(default)]
+    pub code: i64,
+    pub error_subcode: Option<i64>,
+    pub fbtrace_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct GraphErrorResponse {
+    error: GraphErrorBody,
+}
+
+/// `GraphError` is the error returned by `Res::send`.
+///
+/// # Variants
+///
+/// * `RateLimited` - Too many calls were made by the application, the page or to the user.
+/// * `RecipientUnavailable` - The user blocked the page, deleted their account or cannot be found.
+/// * `OutsideWindow` - The message was sent more than 24 hours after the last message of the user.
+/// * `InvalidToken` - The page access token is invalid or expired.
+/// * `Api` - Any other error returned by the Graph API.
+/// * `InvalidResponse` - The Graph API returned a body that could not be parsed.
+/// * `Transport` - The request could not be sent or its response could not be read.
+/// * `Serialize` - The response model could not be serialized to JSON.
+#[derive(Debug)]
+pub enum GraphError {
+    RateLimited(GraphErrorBody),
+    RecipientUnavailable(GraphErrorBody),
+    OutsideWindow(GraphErrorBody),
+    InvalidToken(GraphErrorBody),
+    Api { status: u16, error: GraphErrorBody },
+    InvalidResponse { status: u16, body: String },
+    Transport(reqwest::Error),
+    Serialize(serde_json::Error),
+}
+
+impl GraphError {
+    /// Classifies the error body returned by the Graph API with the HTTP `status`.
+    pub fn from_response(status: u16, body: &str) -> Self {
+        let error = match serde_json::from_str::<GraphErrorResponse>(body) {
+            Ok(response) => response.error,
+            Err(_) => {
+                return Self::InvalidResponse {
+                    status,
+                    body: body.to_owned(),
+                }
+            }
+        };
+
+        match (error.code, error.error_subcode) {
+            (4 | 17 | 32 | 613, _) | (80000..=80014, _) => Self::RateLimited(error),
+            (190 | 102, _) => Self::InvalidToken(error),
+            (10, Some(2018278)) | (2018278, _) => Self::OutsideWindow(error),
+            (551, _) | (100, Some(2018001)) | (200, Some(1545041)) => {
+                Self::RecipientUnavailable(error)
+            }
+            _ => Self::Api { status, error },
+        }
+    }
+
+    /// Returns `true` if sending the request again may succeed.
+    ///
+    /// This is the case for rate limiting, server errors and connection errors. Timeouts are not retried, because the
+    /// Graph API may have sent the message before the request timed out, and the user would receive it twice.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::RateLimited(_) => true,
+            Self::Api { status, .. } | Self::InvalidResponse { status, .. } => *status >= 500,
+            Self::Transport(err) => err.is_connect(),
+            Self::RecipientUnavailable(_)
+            | Self::OutsideWindow(_)
+            | Self::InvalidToken(_)
+            | Self::Serialize(_) => false,
+        }
+    }
+
+    /// Returns the error body of the Graph API, if any.
+    pub fn body(&self) -> Option<&GraphErrorBody> {
+        match self {
+            Self::RateLimited(error)
+            | Self::RecipientUnavailable(error)
+            | Self::OutsideWindow(error)
+            | Self::InvalidToken(error)
+            | Self::Api { error, .. } => Some(error),
+            Self::InvalidResponse { .. } | Self::Transport(_) | Self::Serialize(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for GraphError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::RateLimited(error) => write!(f, "rate limited: {}", error.message),
+            Self::RecipientUnavailable(error) => {
+                write!(f, "recipient unavailable: {}", error.message)
+            }
+            Self::OutsideWindow(error) => {
+                write!(f, "outside the messaging window: {}", error.message)
+            }
+            Self::InvalidToken(error) => write!(f, "invalid access token: {}", error.message),
+            Self::Api { status, error } => write!(
+                f,
+                "graph api error {status} (code {}): {}",
+                error.code, error.message
+            ),
+            Self::InvalidResponse { status, body } => {
+                write!(f, "invalid graph api response {status}: {body}")
+            }
+            Self::Transport(err) => write!(f, "transport error: {err}"),
+            Self::Serialize(err) => write!(f, "invalid response model: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for GraphError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Transport(err) => Some(err),
+            Self::Serialize(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for GraphError {
+    fn from(err: reqwest::Error) -> Self {
+        Self::Transport(err)
+    }
+}
+
+/// `RetryPolicy` specifies how failed calls to the Graph API are retried.
+///
+/// Only rate limiting, server errors and connection errors are retried. The delay between two attempts
+/// grows exponentially from `base_delay` up to `max_delay`, with a random jitter. When the Graph API sends a
+/// `Retry-After` header, in seconds or as an HTTP-date, its delay is used instead, and the call is not retried
+/// if it exceeds `max_delay`.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::time::Duration;
+/// use russenger::prelude::*;
+///
+/// let retry_policy = RetryPolicy {
+///     max_retries: 5,
+///     base_delay: Duration::from_millis(200),
+///     max_delay: Duration::from_secs(10),
+/// };
+/// let no_retry = RetryPolicy::none();
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl RetryPolicy {
+    /// A policy that never retries.
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Self::default()
+        }
+    }
+
+    fn backoff(&self, attempt: u32) -> Duration {
+        let delay = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay);
+        let half = delay / 2;
+        half + half.mul_f64(fastrand::f64())
+    }
+
+    /// Returns the delay before retrying the attempt `attempt`, or `None` if the Graph API asks to wait longer than
+    /// `max_delay`.
+    fn delay(&self, attempt: u32, retry_after: Option<Duration>) -> Option<Duration> {
+        match retry_after {
+            Some(delay) if delay > self.max_delay => None,
+            Some(delay) => Some(delay),
+            None => Some(self.backoff(attempt)),
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+        }
+    }
+}
+
+/// `GraphClient` sends requests to the Graph API on behalf of a page.
+///
+/// It holds a pooled HTTP client, so cloning it is cheap and every clone shares the same connections.
+#[derive(Debug, Clone)]
+pub struct GraphClient {
+    http: reqwest::Client,
+    base_url: String,
+    facebook_api_version: String,
+    page_access_token: String,
+    retry_policy: RetryPolicy,
+}
+
+impl GraphClient {
+    pub fn new(facebook_api_version: &str, page_access_token: &str) -> Self {
+        Self {
+            http: build_http_client(DEFAULT_TIMEOUT),
+            base_url: DEFAULT_BASE_URL.to_owned(),
+            facebook_api_version: facebook_api_version.to_owned(),
+            page_access_token: page_access_token.to_owned(),
+            retry_policy: RetryPolicy::default(),
+        }
+    }
+
+    /// Sets the timeout of every request, including the connection.
+    pub fn timeout(self, timeout: Duration) -> Self {
+        Self {
+            http: build_http_client(timeout),
+            ..self
+        }
+    }
+
+    /// Sets the base URL of the Graph API, to send the requests to a proxy or to a mock server.
+    pub fn base_url(self, base_url: &str) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_owned(),
+            ..self
+        }
+    }
+
+    pub fn retry_policy(self, retry_policy: RetryPolicy) -> Self {
+        Self {
+            retry_policy,
+            ..self
+        }
+    }
+
+    /// Sets the access token of the page the requests are sent for, keeping the same connection pool.
+    pub fn page_access_token(self, page_access_token: &str) -> Self {
+        Self {
+            page_access_token: page_access_token.to_owned(),
+            ..self
+        }
+    }
+
+    /// Returns `true` when a page access token is set.
+    pub(crate) fn has_page_access_token(&self) -> bool {
+        !self.page_access_token.is_empty()
+    }
+
+    /// Posts `body` to the `endpoint` of the page, retrying according to the `RetryPolicy`.
+    pub async fn post<T: Serialize>(
+        &self,
+        endpoint: &str,
+        body: &T,
+    ) -> Result<SendResponse, GraphError> {
+        let span = info_span!("graph.send", endpoint, message_id = Empty, error = Empty);
+        let start = Instant::now();
+        let result = self
+            .post_with_retries(endpoint, body)
+            .instrument(span.clone())
+            .await;
+        metrics::graph_request(endpoint, start.elapsed(), result.as_ref().map(|_| ()));
+        match &result {
+            Ok(response) => {
+                if let Some(message_id) = &response.message_id {
+                    span.record("message_id", message_id.as_str());
+                }
+            }
+            Err(err) => {
+                span.record("error", tracing::field::display(err));
+            }
+        }
+        result
+    }
+
+    async fn post_with_retries<T: Serialize>(
+        &self,
+        endpoint: &str,
+        body: &T,
+    ) -> Result<SendResponse, GraphError> {
+        let mut attempt = 0;
+        loop {
+            let (result, retry_after) = self.post_once(endpoint, body).await;
+            let err = match result {
+                Ok(response) => return Ok(response),
+                Err(err) => err,
+            };
+            if attempt >= self.retry_policy.max_retries || !err.is_retryable() {
+                return Err(err);
+            }
+            let Some(delay) = self.retry_policy.delay(attempt, retry_after) else {
+                return Err(err);
+            };
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    /// Checks that the page access token is valid, by fetching the page it belongs to.
+    pub async fn check_token(&self) -> Result<(), GraphError> {
+        let url = format!(
+            "{base_url}/{version}/me",
+            base_url = self.base_url,
+            version = self.facebook_api_version,
+        );
+        let response = self
+            .http
+            .get(url)
+            .query(&[("access_token", &self.page_access_token)])
+            .send()
+            .await?;
+
+        let status = response.status().as_u16();
+        let body = response.text().await?;
+        if (200..300).contains(&status) {
+            Ok(())
+        } else {
+            Err(GraphError::from_response(status, &body))
+        }
+    }
+
+    /// Downloads the content at `url`, like the attachments sent by the users, with the timeout of the client.
+    pub(crate) async fn download(&self, url: &str) -> Result<Vec<u8>, GraphError> {
+        let response = self.http.get(url).send().await?.error_for_status()?;
+        Ok(response.bytes().await?.to_vec())
+    }
+
+    async fn post_once<T: Serialize>(
+        &self,
+        endpoint: &str,
+        body: &T,
+    ) -> (Result<SendResponse, GraphError>, Option<Duration>) {
+        let url = format!(
+            "{base_url}/{version}/me/{endpoint}",
+            base_url = self.base_url,
+            version = self.facebook_api_version,
+        );
+        let response = match self
+            .http
+            .post(url)
+            .query(&[("access_token", &self.page_access_token)])
+            .json(body)
+            .send()
+            .await
+        {
+            Ok(response) => response,
+            Err(err) => return (Err(err.into()), None),
+        };
+
+        let status = response.status().as_u16();
+        let retry_after = response
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| parse_retry_after(value, SystemTime::now()));
+        let body = match response.text().await {
+            Ok(body) => body,
+            Err(err) => return (Err(err.into()), retry_after),
+        };
+
+        let result = if (200..300).contains(&status) {
+            serde_json::from_str(&body).map_err(|_| GraphError::InvalidResponse { status, body })
+        } else {
+            Err(GraphError::from_response(status, &body))
+        };
+        (result, retry_after)
+    }
+}
+
+/// Parses a `Retry-After` header, given either as a number of seconds or as an HTTP-date. A date in the past means
+/// no delay.
+fn parse_retry_after(value: &str, now: SystemTime) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = httpdate::parse_http_date(value).ok()?;
+    Some(date.duration_since(now).unwrap_or(Duration::ZERO))
+}
+
+fn build_http_client(timeout: Duration) -> reqwest::Client {
+    reqwest::Client::builder()
+        .timeout(timeout)
+        .connect_timeout(timeout)
+        .build()
+        .expect("Failed to build the HTTP client")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+
+    use super::*;
+
+    fn error(code: i64, error_subcode: Option<i64>) -> GraphError {
+        let body = serde_json::json!({
+            "error": {
+                "message": "error",
+                "type": "OAuthException",
+                "code": code,
+                "error_subcode": error_subcode,
+                "fbtrace_id": "trace",
+            }
+        });
+        GraphError::from_response(400, &body.to_string())
+    }
+
+    #[test]
+    fn classifies_rate_limits() {
+        for code in [4, 17, 32, 613, 80000, 80006, 80014] {
+            let error = error(code, None);
+            assert!(
+                matches!(error, GraphError::RateLimited(_)),
+                "{code}: {error:?}"
+            );
+            assert!(error.is_retryable());
+        }
+    }
+
+    #[test]
+    fn classifies_invalid_tokens() {
+        for code in [190, 102] {
+            let error = error(code, Some(463));
+            assert!(
+                matches!(error, GraphError::InvalidToken(_)),
+                "{code}: {error:?}"
+            );
+            assert!(!error.is_retryable());
+        }
+    }
+
+    #[test]
+    fn classifies_messages_outside_the_window() {
+        for (code, error_subcode) in [(10, Some(2018278)), (2018278, None)] {
+            let error = error(code, error_subcode);
+            assert!(
+                matches!(error, GraphError::OutsideWindow(_)),
+                "{code}: {error:?}"
+            );
+            assert!(!error.is_retryable());
+        }
+        assert!(matches!(error(10, None), GraphError::Api { .. }));
+    }
+
+    #[test]
+    fn classifies_unavailable_recipients() {
+        for (code, error_subcode) in [(551, None), (100, Some(2018001)), (200, Some(1545041))] {
+            let error = error(code, error_subcode);
+            assert!(
+                matches!(error, GraphError::RecipientUnavailable(_)),
+                "{code}: {error:?}"
+            );
+            assert!(!error.is_retryable());
+        }
+    }
+
+    #[test]
+    fn classifies_other_errors() {
+        let error = error(100, None);
+        assert!(matches!(error, GraphError::Api { status: 400, .. }));
+        assert!(!error.is_retryable());
+        assert_eq!(error.body().map(|body| body.code), Some(100));
+
+        let error = GraphError::from_response(502, "<html>Bad Gateway</html>");
+        assert!(matches!(
+            error,
+            GraphError::InvalidResponse { status: 502, .. }
+        ));
+        assert!(error.is_retryable());
+        assert!(error.body().is_none());
+    }
+
+    #[test]
+    fn honours_retry_after() {
+        let policy = RetryPolicy {
+            max_retries: 3,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(10),
+        };
+
+        assert_eq!(
+            policy.delay(0, Some(Duration::from_secs(3))),
+            Some(Duration::from_secs(3))
+        );
+        assert_eq!(policy.delay(2, Some(Duration::ZERO)), Some(Duration::ZERO));
+        assert_eq!(policy.delay(0, Some(Duration::from_secs(11))), None);
+    }
+
+    #[test]
+    fn parses_retry_after_in_seconds() {
+        let now = SystemTime::now();
+        assert_eq!(
+            parse_retry_after("120", now),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(parse_retry_after(" 0 ", now), Some(Duration::ZERO));
+        assert_eq!(parse_retry_after("-1", now), None);
+        assert_eq!(parse_retry_after("soon", now), None);
+    }
+
+    #[test]
+    fn parses_retry_after_as_an_http_date() {
+        let now = httpdate::parse_http_date("Wed, 21 Oct 2015 07:28:00 GMT").unwrap();
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:30 GMT", now),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:27:00 GMT", now),
+            Some(Duration::ZERO)
+        );
+    }
+
+    #[test]
+    fn backs_off_exponentially_up_to_the_max_delay() {
+        let policy = RetryPolicy {
+            max_retries: 10,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(1),
+        };
+
+        for (attempt, delay) in [(0, 100), (1, 200), (2, 400), (3, 800), (4, 1000), (9, 1000)] {
+            let delay = Duration::from_millis(delay);
+            let backoff = policy.delay(attempt, None).unwrap();
+            assert!(
+                backoff >= delay / 2 && backoff <= delay,
+                "{attempt}: {backoff:?}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn retries_connection_errors_but_not_timeouts() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let timeout = build_http_client(Duration::from_millis(100))
+            .get(format!("http://{addr}"))
+            .send()
+            .await
+            .unwrap_err();
+        assert!(timeout.is_timeout());
+        assert!(!GraphError::from(timeout).is_retryable());
+
+        drop(listener);
+        let refused = reqwest::get(format!("http://{addr}")).await.unwrap_err();
+        assert!(refused.is_connect());
+        assert!(GraphError::from(refused).is_retryable());
+    }
+}
@@ -0,0 +1,155 @@
+//! The `middleware` module contains the `Middleware` trait, which wraps the actions of a `Router`.
+//!
+//! A middleware runs before and after an action. It can modify the `Req` given to the action, or
+//! short-circuit it by returning without calling `next.run`. Middlewares are attached globally with
+//! `App::middleware` or to a group of routes with `Router::middleware`. Global middlewares run first,
+//! then the middlewares of the router, in the order they were added.
+//!
+//! # Built-in middlewares
+//!
+//! * `TypingIndicator`: Marks the message as seen and shows the typing indicator while the action runs.
+//! * `Logger`: Logs the path and the duration of every action.
+//!
+//! # Examples
+//!
+//! Sending a typing indicator for every action and rejecting users that are not registered:
+//!
+//! ```rust,no_run
+//! use russenger::prelude::*;
+//!
+//! async fn index(res: Res, req: Req) -> Result<()> {
+//!     res.send(TextModel::new(&req.user, "Welcome back!")).await?;
+//!     Ok(())
+//! }
+//!
+//! async fn registered_only(res: Res, req: Req, next: Next) -> Result<()> {
+//!     if req.user.is_empty() {
+//!         res.send(TextModel::new(&req.user, "Please register first")).await?;
+//!         return Ok(());
+//!     }
+//!     next.run(res, req).await
+//! }
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<()> {
+//!     App::init().await?
+//!         .middleware(Logger)
+//!         .attach(
+//!             Router::new()
+//!                 .middleware(TypingIndicator)
+//!                 .middleware(registered_only)
+//!                 .add("/", index),
+//!         )
+//!         .launch()
+//!         .await?;
+//!     Ok(())
+//! }
+//! ```
+use std::{future::Future, sync::Arc, time::Instant};
+
+use crate::core::{
+    request::Req,
+    response::Res,
+    router::{Action, FutureResult},
+};
+use crate::error::Result;
+use crate::response_models::sender_action::{Actions, SenderActionModel};
+
+/// `Middleware` wraps the actions of a `Router`.
+///
+/// It is implemented for every `Fn(Res, Req, Next)` returning a future, so an `async fn` can be used as a middleware.
+pub trait Middleware: Send + Sync + 'static {
+    fn call(&self, res: Res, req: Req, next: Next) -> FutureResult;
+}
+
+impl<F, Fut> Middleware for F
+where
+    F: Fn(Res, Req, Next) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<()>> + Send + 'static,
+{
+    fn call(&self, res: Res, req: Req, next: Next) -> FutureResult {
+        Box::pin(self(res, req, next))
+    }
+}
+
+/// `Next` runs the remaining middlewares and then the action.
+pub struct Next {
+    middlewares: Arc<[Arc<dyn Middleware>]>,
+    index: usize,
+    action: Action,
+}
+
+impl Next {
+    /// Runs the next middleware, or the action once every middleware has run.
+    pub async fn run(mut self, res: Res, req: Req) -> Result<()> {
+        match self.middlewares.get(self.index).cloned() {
+            Some(middleware) => {
+                self.index += 1;
+                middleware.call(res, req, self).await
+            }
+            None => (self.action)(res, req).await,
+        }
+    }
+}
+
+/// Wraps `action` so that `middlewares` run around it.
+pub(crate) fn wrap(middlewares: &[Arc<dyn Middleware>], action: Action) -> Action {
+    if middlewares.is_empty() {
+        return action;
+    }
+    let middlewares: Arc<[Arc<dyn Middleware>]> = middlewares.into();
+    Arc::new(move |res: Res, req: Req| -> FutureResult {
+        let next = Next {
+            middlewares: middlewares.clone(),
+            index: 0,
+            action: action.clone(),
+        };
+        Box::pin(next.run(res, req))
+    })
+}
+
+/// `TypingIndicator` marks the message of the user as seen and shows the typing indicator while the action runs.
+pub struct TypingIndicator;
+
+impl Middleware for TypingIndicator {
+    fn call(&self, res: Res, req: Req, next: Next) -> FutureResult {
+        Box::pin(async move {
+            let user = req.user.clone();
+            for action in [Actions::MarkSeen, Actions::TypingOn] {
+                if let Err(err) = res.send(SenderActionModel::new(&user, action)).await {
+                    tracing::warn!(error = %err, "Error sending sender action");
+                }
+            }
+            let result = next.run(res.clone(), req).await;
+            if let Err(err) = res
+                .send(SenderActionModel::new(&user, Actions::TypingOff))
+                .await
+            {
+                tracing::warn!(error = %err, "Error sending sender action");
+            }
+            result
+        })
+    }
+}
+
+/// `Logger` logs the path and the duration of every action, along with its error if it failed.
+///
+/// The logs are recorded in the `event` span of the action, which carries the user, hashed when
+/// `App::hash_user_ids` is set.
+pub struct Logger;
+
+impl Middleware for Logger {
+    fn call(&self, res: Res, req: Req, next: Next) -> FutureResult {
+        Box::pin(async move {
+            let path = req.path.clone();
+            let start = Instant::now();
+            let result = next.run(res, req).await;
+            let elapsed = start.elapsed();
+            match &result {
+                Ok(()) => tracing::info!(path, ?elapsed, "Action finished"),
+                Err(err) => tracing::error!(path, ?elapsed, error = %err, "Action failed"),
+            }
+            result
+        })
+    }
+}
@@ -4,7 +4,9 @@
 //!
 //! # Submodules
 //!
-//! * `action`: This module contains the `Action` trait and the `ACTION_REGISTRY`.
+//! * `extract`: This module contains the `FromRequest` trait and the extractors used by the arguments of an `#[action]`.
+//! * `graph`: This module contains the `SendResponse` and `GraphError` types returned when sending a response.
+//! * `middleware`: This module contains the `Middleware` trait that wraps the actions of a router.
 //! * `app_state`: This module contains the `AppState` struct that represents the state of the application.
 //! * `request`: This module contains the `Req` struct that represents a request from a user.
 //! * `response`: This module contains the `Res` struct that represents a response that can be sent to a user.
@@ -26,6 +28,9 @@
 //! }
 //! ```
 
+pub mod extract;
+pub mod graph;
+pub mod middleware;
 pub mod request;
 pub mod response;
 pub mod router;
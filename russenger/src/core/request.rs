@@ -5,6 +5,12 @@
 //! * `query`: A `Query` that represents the query made by the user.
 //! * `data`: A `Data` that represents the data associated with the request.
 //! * `host`: A `String` that represents the host from which the request was made.
+//! * `attachments`: The attachments sent by the user with their message.
+//! * `params`: The parameters captured by the route pattern of the action.
+//! * `path`: The path of the action handling the request.
+//! * `page_id`: The id of the Facebook page the user wrote to.
+//!
+//! The values kept for the user between their actions are available through `req.session()`.
 //!
 //! # Examples
 //!
@@ -51,8 +57,10 @@
 //! ```
 use std::sync::Arc;
 
-use crate::db::Query;
+use crate::core::router::Params;
+use crate::db::{Query, Session};
 use crate::response_models::data::Data;
+use crate::services::Attachment;
 
 /// The `Req` struct represents a request from a user.
 ///
@@ -61,6 +69,10 @@ use crate::response_models::data::Data;
 /// * `query`: A `Query` that represents the query made by the user.
 /// * `data`: A `Data` that represents the data associated with the request.
 /// * `host`: A `String` that represents the host from which the request was made.
+/// * `attachments`: The attachments sent by the user with their message.
+/// * `params`: The parameters captured by the route pattern of the action.
+/// * `path`: The path of the action handling the request.
+/// * `page_id`: The id of the Facebook page the user wrote to.
 #[derive(Clone)]
 pub struct Req {
     /// The user who made the request.
@@ -163,6 +175,72 @@ pub struct Req {
     /// }
     /// ```
     pub host: String,
+
+    /// The attachments sent by the user with their message.
+    ///
+    /// This field holds the images, audio, videos, files, stickers and locations of the message. It is empty for
+    /// postbacks, quick replies and plain text messages.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use russenger::prelude::*;
+    ///
+    /// async fn index(res: Res, req: Req) -> Result<()> {
+    ///     if let Some(attachment) = req.attachments.first() {
+    ///         let bytes = attachment.download(&res).await?;
+    ///         res.send(TextModel::new(&req.user, format!("Received {} bytes", bytes.len()))).await?;
+    ///     }
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub attachments: Vec<Attachment>,
+
+    /// The parameters captured by the route pattern of the action.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use russenger::prelude::*;
+    ///
+    /// async fn product(res: Res, req: Req) -> Result<()> {
+    ///     let id: u32 = req.params.parse("id")?;
+    ///     res.send(TextModel::new(&req.user, format!("Product {id}"))).await?;
+    ///
+    ///     Ok(())
+    /// }
+    ///
+    /// let router = Router::new().add("/product/:id", product);
+    /// ```
+    pub params: Params,
+
+    /// The path of the action handling the request.
+    ///
+    /// For the fallback of a `Router`, this is the path that did not match any route.
+    pub path: String,
+
+    /// The id of the Facebook page the user wrote to.
+    ///
+    /// When an `App` serves several pages, the responses are sent with the access token of this page, and the users
+    /// and their paths are kept apart for every page.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use russenger::prelude::*;
+    ///
+    /// async fn index(res: Res, req: Req) -> Result<()> {
+    ///     let message = match req.page_id.as_str() {
+    ///         "1234567890" => "Welcome to the shop",
+    ///         _ => "Welcome to the blog",
+    ///     };
+    ///     res.send(TextModel::new(&req.user, message)).await?;
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub page_id: String,
 }
 
 impl Req {
@@ -184,9 +262,37 @@ impl Req {
             query,
             data,
             host: host.to_owned(),
+            attachments: Vec::new(),
+            params: Params::default(),
+            path: "/".to_owned(),
+            page_id: String::new(),
         }
     }
 
+    /// Returns the session of the user, which stores values between their actions.
+    ///
+    /// The values are serialized to JSON, scoped to the user and to their page, and can expire. See
+    /// [`Session`](crate::db::Session).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use russenger::prelude::*;
+    ///
+    /// async fn add_to_cart(res: Res, req: Req) -> Result<()> {
+    ///     let product: String = req.data.get_value()?;
+    ///     let mut cart: Vec<String> = req.session().get("cart").await?.unwrap_or_default();
+    ///     cart.push(product);
+    ///     req.session().set("cart", &cart).await?;
+    ///     res.send(TextModel::new(&req.user, format!("{} products in your cart", cart.len()))).await?;
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn session(&self) -> Session {
+        Session::new(self.query.clone(), &self.user)
+    }
+
     /// Creates a new `Req` instance with updated data.
     ///
     /// This method allows you to create an updated version of the current `Req` object,
@@ -15,12 +15,15 @@
 //!     Ok(())
 //! }
 //! ```
-use reqwest::Response;
-use serde::Serialize;
-use std::sync::Arc;
+use std::{str::FromStr, sync::Arc};
 
+use serde_json::Value;
+use tracing::warn;
+
+use crate::core::graph::{GraphClient, GraphError, SendResponse};
+use crate::db::{Query, MAX_PAYLOAD_LENGTH};
 use crate::error::Result;
-use crate::{db::Query, response_models::ResponseModel};
+use crate::response_models::{payload::Payload, ResponseModel};
 
 /// The `Res` struct represents a response that can be sent to a user.
 ///
@@ -43,12 +46,13 @@ use crate::{db::Query, response_models::ResponseModel};
 ///
 /// # Methods
 ///
-/// * `send`: Sends a response to a user. It takes a `ResponseModel` as an argument and returns a `SendResult`.
+/// * `send`: Sends a response to a user. It takes a `ResponseModel` as an argument and returns a `SendResponse` or a `GraphError`.
+#[derive(Clone)]
 pub struct Res {
     query: Arc<Query>,
     sender_id: String,
-    facebook_api_version: String,
-    page_access_token: String,
+    graph: GraphClient,
+    payload_secret: Option<Arc<str>>,
 }
 
 impl Res {
@@ -60,51 +64,127 @@ impl Res {
     ///
     /// # Returns
     ///
-    /// A `SendResult` that represents the result of the send operation.
+    /// A `SendResponse` that contains the ids of the recipient and of the sent message.
     ///
     /// # Errors
     ///
-    /// Returns `SendResult::Error` if the send operation fails.
-    pub async fn send<T: ResponseModel>(&self, response_model: T) -> Result<String, String> {
-        let url_api = format!(
-            "https://graph.facebook.com/{version}/me/{endpoint}?access_token={token}",
-            endpoint = response_model.get_endpoint(),
-            version = self.facebook_api_version,
-            token = self.page_access_token
-        );
+    /// Returns a `GraphError` if the response model can't be serialized, if the request fails or if the Graph API
+    /// returns an error.
+    pub async fn send<T: ResponseModel>(
+        &self,
+        response_model: T,
+    ) -> Result<SendResponse, GraphError> {
+        let endpoint = response_model.get_endpoint();
+        let mut body = serde_json::to_value(&response_model).map_err(GraphError::Serialize)?;
+        self.prepare_payloads(&mut body).await;
+        self.graph.post(endpoint, &body).await
+    }
 
-        match fetch_post(&url_api, response_model).await {
-            Ok(response) => {
-                if response.status().is_success() {
-                    Ok(response.text().await.unwrap())
-                } else {
-                    Err(response.text().await.unwrap())
+    /// Signs the payloads of `body` with the payload secret, if it is set, and replaces the payloads longer than
+    /// `MAX_PAYLOAD_LENGTH` with payloads carrying the key of their stored data.
+    async fn prepare_payloads(&self, body: &mut Value) {
+        let sign = |payload: Payload| match &self.payload_secret {
+            Some(secret) => payload.sign(secret),
+            None => payload,
+        };
+        let mut payloads = Vec::new();
+        find_payloads(body, &mut payloads);
+        for value in payloads {
+            let is_long = value
+                .as_str()
+                .is_some_and(|payload| payload.len() > MAX_PAYLOAD_LENGTH);
+            if self.payload_secret.is_none() && !is_long {
+                continue;
+            }
+            let Some(Ok(payload)) = value.as_str().map(Payload::from_str) else {
+                continue;
+            };
+            let mut payload = sign(payload);
+            if payload.to_string().len() > MAX_PAYLOAD_LENGTH {
+                match self.query.store_payload(&payload.get_data()).await {
+                    Ok(key) => payload = sign(payload.stored(key)),
+                    Err(err) => warn!(error = %err, "Error storing the data of a long payload"),
                 }
             }
-            Err(err) => Err(err.to_string()),
+            *value = Value::String(payload.to_string());
         }
     }
 
-    pub fn new(
-        sender_id: &str,
-        query: Arc<Query>,
-        facebook_api_version: String,
-        page_access_token: String,
-    ) -> Self {
+    pub fn new(sender_id: &str, query: Arc<Query>, graph: GraphClient) -> Self {
         Self {
             query,
             sender_id: sender_id.to_owned(),
-            facebook_api_version,
-            page_access_token,
+            graph,
+            payload_secret: None,
+        }
+    }
+
+    /// Signs the payloads sent by this `Res` with `secret`.
+    pub(crate) fn sign_payloads(self, secret: Option<Arc<str>>) -> Self {
+        Self {
+            payload_secret: secret,
+            ..self
         }
     }
 
+    /// The `GraphClient` the responses are sent with.
+    pub(crate) fn graph(&self) -> &GraphClient {
+        &self.graph
+    }
+
     pub async fn redirect(&self, path: &str) -> Result<()> {
         self.query.set_path(&self.sender_id, path).await?;
         Ok(())
     }
+
+    /// Sends the user back to their previous step.
+    ///
+    /// The current step is removed from the history of the user, and their next message is handled by the action of
+    /// the previous step. The user is sent to `/` when there is no previous step. To run the previous action right
+    /// away, send a [`BackModel`](crate::response_models::next::BackModel) instead.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use russenger::prelude::*;
+    ///
+    /// async fn get_email(res: Res, req: Req) -> Result<()> {
+    ///     let email: String = req.data.get_value()?;
+    ///     if email == "cancel" {
+    ///         res.send(TextModel::new(&req.user, "Enter your name again")).await?;
+    ///         return res.back().await;
+    ///     }
+    ///     res.send(TextModel::new(&req.user, "Thank you")).await?;
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn back(&self) -> Result<()> {
+        let path = match self.query.back(&self.sender_id).await? {
+            Some(step) => step.path,
+            None => "/".to_owned(),
+        };
+        self.redirect(&path).await
+    }
 }
 
-async fn fetch_post<T: Serialize>(url: &str, body: T) -> Result<Response> {
-    Ok(reqwest::Client::new().post(url).json(&body).send().await?)
+/// Collects the `payload` strings of `value`.
+fn find_payloads<'v>(value: &'v mut Value, found: &mut Vec<&'v mut Value>) {
+    match value {
+        Value::Object(fields) => {
+            for (name, value) in fields.iter_mut() {
+                if name == "payload" && value.is_string() {
+                    found.push(value);
+                } else {
+                    find_payloads(value, found);
+                }
+            }
+        }
+        Value::Array(values) => {
+            for value in values {
+                find_payloads(value, found);
+            }
+        }
+        _ => {}
+    }
 }
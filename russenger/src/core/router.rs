@@ -1,12 +1,147 @@
-use std::{collections::HashMap, future::Future, pin::Pin};
+use std::{collections::HashMap, future::Future, io, pin::Pin, str::FromStr, sync::Arc};
 
-use crate::core::{request::Req, response::Res};
-use crate::error::Result;
+use crate::core::{
+    middleware::{self, Middleware},
+    request::Req,
+    response::Res,
+};
+use crate::error::{Error, Result};
+use crate::response_models::payload::RussengerPayload;
 
-type FutureResult = Pin<Box<dyn Future<Output = Result<()>> + Send>>;
+pub type FutureResult = Pin<Box<dyn Future<Output = Result<()>> + Send>>;
+
+pub(crate) type Action = Arc<dyn Fn(Res, Req) -> FutureResult + Send + Sync>;
+
+pub(crate) type ErrorHandler = Arc<dyn Fn(Res, Req, String, Error) -> FutureResult + Send + Sync>;
+
+/// The parameters captured by a route pattern such as `/product/:id` or `/help/*rest`.
+#[derive(Debug, Clone, Default)]
+pub struct Params(HashMap<String, String>);
+
+impl Params {
+    /// Returns the raw value of the parameter `name`.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0.get(name).map(String::as_str)
+    }
+
+    /// Parses the parameter `name` into `T`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the route has no parameter `name` or if its value cannot be parsed.
+    pub fn parse<T>(&self, name: &str) -> Result<T>
+    where
+        T: FromStr,
+        T::Err: std::error::Error + Send + Sync + 'static,
+    {
+        let value = self.get(name).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("Parameter {name} not found"),
+            )
+        })?;
+        Ok(value.parse()?)
+    }
+}
+
+enum Segment {
+    Static(String),
+    Param(String),
+    Wildcard(String),
+}
+
+pub(crate) struct Pattern {
+    route: String,
+    segments: Vec<Segment>,
+}
+
+impl Pattern {
+    /// Parses `path`, or returns `None` if it has no parameter nor wildcard.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a wildcard is not the last segment, or if two parameters have the same name.
+    fn parse(path: &str) -> Option<Self> {
+        let segments: Vec<Segment> = split(path)
+            .map(|segment| {
+                if let Some(name) = segment.strip_prefix(':') {
+                    Segment::Param(name.to_owned())
+                } else if let Some(name) = segment.strip_prefix('*') {
+                    Segment::Wildcard(name.to_owned())
+                } else {
+                    Segment::Static(segment.to_owned())
+                }
+            })
+            .collect();
+
+        let mut names = Vec::new();
+        for (index, segment) in segments.iter().enumerate() {
+            let name = match segment {
+                Segment::Static(_) => continue,
+                Segment::Param(name) => name,
+                Segment::Wildcard(name) => {
+                    if index + 1 != segments.len() {
+                        panic!(
+                            "Invalid route {path}: the wildcard *{name} must be the last segment"
+                        );
+                    }
+                    name
+                }
+            };
+            if names.contains(&name) {
+                panic!("Invalid route {path}: the parameter {name} is captured twice");
+            }
+            names.push(name);
+        }
+
+        (!names.is_empty()).then(|| Self {
+            route: path.to_owned(),
+            segments,
+        })
+    }
+
+    fn matches(&self, path: &str) -> Option<Params> {
+        let mut params = HashMap::new();
+        let mut parts = split(path);
+        for segment in &self.segments {
+            match segment {
+                Segment::Static(expected) => {
+                    if parts.next()? != expected {
+                        return None;
+                    }
+                }
+                Segment::Param(name) => {
+                    params.insert(name.clone(), parts.next()?.to_owned());
+                }
+                Segment::Wildcard(name) => {
+                    params.insert(name.clone(), parts.by_ref().collect::<Vec<_>>().join("/"));
+                }
+            }
+        }
+        parts.next().is_none().then_some(Params(params))
+    }
+}
+
+fn split(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|segment| !segment.is_empty())
+}
+
+fn boxed<F, Fut>(action: F) -> Action
+where
+    F: Fn(Res, Req) -> Fut + 'static + Send + Sync,
+    Fut: Future<Output = Result<()>> + Send + 'static,
+{
+    Arc::new(move |res: Res, req: Req| -> FutureResult { Box::pin(action(res, req)) })
+}
 
 pub struct Router {
-    pub(crate) routes: HashMap<String, Box<dyn Fn(Res, Req) -> FutureResult + Send + Sync>>,
+    pub(crate) routes: HashMap<String, Action>,
+    pub(crate) patterns: Vec<(Pattern, Action)>,
+    pub(crate) fallback: Option<Action>,
+    pub(crate) reset_unknown_path: bool,
+    pub(crate) error_handler: Option<ErrorHandler>,
+    pub(crate) rejection_handler: Option<ErrorHandler>,
+    pub(crate) middlewares: Vec<Arc<dyn Middleware>>,
 }
 
 impl Router {
@@ -14,20 +149,194 @@ impl Router {
     pub fn new() -> Self {
         Self {
             routes: HashMap::new(),
+            patterns: Vec::new(),
+            fallback: None,
+            reset_unknown_path: false,
+            error_handler: None,
+            rejection_handler: None,
+            middlewares: Vec::new(),
         }
     }
 
+    /// Adds an action for `path` to the router.
+    ///
+    /// The path can contain parameters, such as `/product/:id`, and end with a wildcard, such as `/help/*rest`,
+    /// which captures the remaining segments. The captured values are available through `req.params`.
+    /// Exact paths are always matched before patterns, and patterns are tried in the order they were added.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a wildcard is not the last segment of `path`, or if `path` names the same parameter twice.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use russenger::prelude::*;
+    ///
+    /// async fn product(res: Res, req: Req) -> Result<()> {
+    ///     let id: u32 = req.params.parse("id")?;
+    ///     res.send(TextModel::new(&req.user, format!("Product {id}"))).await?;
+    ///     Ok(())
+    /// }
+    ///
+    /// async fn help(res: Res, req: Req) -> Result<()> {
+    ///     let topic = req.params.get("rest").unwrap_or_default();
+    ///     res.send(TextModel::new(&req.user, format!("Help about {topic}"))).await?;
+    ///     Ok(())
+    /// }
+    ///
+    /// let router = Router::new()
+    ///     .add("/product/:id", product)
+    ///     .add("/help/*rest", help);
+    /// ```
     pub fn add<F, Fut>(mut self, path: &str, action: F) -> Self
     where
         F: Fn(Res, Req) -> Fut + 'static + Send + Sync,
         Fut: Future<Output = Result<()>> + Send + 'static,
     {
-        let boxed =
-            Box::new(move |res: Res, req: Req| -> FutureResult { Box::pin(action(res, req)) });
+        match Pattern::parse(path) {
+            Some(pattern) => self.patterns.push((pattern, boxed(action))),
+            None => {
+                self.routes.insert(path.to_owned(), boxed(action));
+            }
+        }
+        self
+    }
+
+    /// Adds an action for every variant of the payload enum `P`, which receives the decoded variant.
+    ///
+    /// The variant is decoded from the `Data` of the payload. If the user sends a text message while their path is one
+    /// of these paths, the `Data` holds the text instead and the action fails with a decoding error. See
+    /// [`RussengerPayload`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use russenger::prelude::*;
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(RussengerPayload, Serialize, Deserialize)]
+    /// enum Color {
+    ///     Red,
+    ///     Custom(String),
+    /// }
+    ///
+    /// async fn color(res: Res, req: Req, color: Color) -> Result<()> {
+    ///     let name = match color {
+    ///         Color::Red => "red".to_owned(),
+    ///         Color::Custom(name) => name,
+    ///     };
+    ///     res.send(TextModel::new(&req.user, format!("You chose {name}"))).await?;
+    ///     Ok(())
+    /// }
+    ///
+    /// let router = Router::new().payload(color);
+    /// ```
+    pub fn payload<P, F, Fut>(mut self, action: F) -> Self
+    where
+        P: RussengerPayload,
+        F: Fn(Res, Req, P) -> Fut + 'static + Send + Sync,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        let action = Arc::new(action);
+        for path in P::PATHS {
+            let action = action.clone();
+            self = self.add(path, move |res, req: Req| {
+                let action = action.clone();
+                async move {
+                    let payload = req.data.get_value::<P>()?;
+                    action(res, req, payload).await
+                }
+            });
+        }
+        self
+    }
+
+    /// Sets the action run when no route matches the path of a payload or the path stored for the user.
+    ///
+    /// The unmatched path is available through `req.path`. Without a fallback, the event is only reported as an error.
+    /// Only one of the routers attached to an `App` can set a fallback, see [`App::attach`](crate::App::attach).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use russenger::prelude::*;
+    ///
+    /// async fn not_found(res: Res, req: Req) -> Result<()> {
+    ///     res.send(TextModel::new(&req.user, format!("{} is not available anymore", req.path))).await?;
+    ///     Ok(())
+    /// }
+    ///
+    /// let router = Router::new()
+    ///     .fallback(not_found)
+    ///     .reset_unknown_path();
+    /// ```
+    pub fn fallback<F, Fut>(mut self, action: F) -> Self
+    where
+        F: Fn(Res, Req) -> Fut + 'static + Send + Sync,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        self.fallback = Some(boxed(action));
+        self
+    }
+
+    /// Redirects the user to `/` when no route matches their path, before the fallback runs.
+    ///
+    /// This keeps users from being stuck on a route that was removed from the application. It must be set on the
+    /// router that sets the fallback, if any, see [`App::attach`](crate::App::attach).
+    pub fn reset_unknown_path(mut self) -> Self {
+        self.reset_unknown_path = true;
+        self
+    }
 
-        self.routes.insert(path.to_owned(), boxed);
+    /// Adds a middleware that runs around every action of this router, including its fallback.
+    ///
+    /// Middlewares run in the order they were added. See the [`middleware`](crate::core::middleware) module.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use russenger::prelude::*;
+    ///
+    /// async fn index(res: Res, req: Req) -> Result<()> {
+    ///     res.send(TextModel::new(&req.user, "Hello")).await?;
+    ///     Ok(())
+    /// }
+    ///
+    /// let router = Router::new()
+    ///     .middleware(TypingIndicator)
+    ///     .add("/", index);
+    /// ```
+    pub fn middleware(mut self, middleware: impl Middleware) -> Self {
+        self.middlewares.push(Arc::new(middleware));
         self
     }
+
+    /// Wraps every action of the router with its middlewares, which are then cleared.
+    pub(crate) fn apply_middlewares(&mut self) {
+        let middlewares = std::mem::take(&mut self.middlewares);
+        for action in self.routes.values_mut() {
+            *action = middleware::wrap(&middlewares, action.clone());
+        }
+        for (_, action) in self.patterns.iter_mut() {
+            *action = middleware::wrap(&middlewares, action.clone());
+        }
+        if let Some(action) = self.fallback.take() {
+            self.fallback = Some(middleware::wrap(&middlewares, action));
+        }
+    }
+
+    /// Finds the action of `path`, along with the route it matched and the captured parameters.
+    pub(crate) fn find(&self, path: &str) -> Option<(&str, &Action, Params)> {
+        if let Some((route, action)) = self.routes.get_key_value(path) {
+            return Some((route, action, Params::default()));
+        }
+        self.patterns.iter().find_map(|(pattern, action)| {
+            pattern
+                .matches(path)
+                .map(|params| (pattern.route.as_str(), action, params))
+        })
+    }
 }
 
 #[macro_export]
@@ -41,3 +350,103 @@ macro_rules! router {
         }
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn noop(_: Res, _: Req) -> Result<()> {
+        Ok(())
+    }
+
+    fn matched_route(router: &Router, path: &str) -> Option<String> {
+        router.find(path).map(|(route, _, _)| route.to_owned())
+    }
+
+    fn params(pattern: &str, path: &str) -> Option<Params> {
+        Pattern::parse(pattern)
+            .expect("Not a pattern")
+            .matches(path)
+    }
+
+    #[test]
+    fn exact_routes_beat_patterns() {
+        let router = Router::new()
+            .add("/product/:id", noop)
+            .add("/product/new", noop);
+
+        assert_eq!(
+            matched_route(&router, "/product/new").as_deref(),
+            Some("/product/new")
+        );
+        assert_eq!(
+            matched_route(&router, "/product/42").as_deref(),
+            Some("/product/:id")
+        );
+        assert_eq!(matched_route(&router, "/products"), None);
+    }
+
+    #[test]
+    fn patterns_are_tried_in_order() {
+        let router = Router::new()
+            .add("/product/:id", noop)
+            .add("/product/:name", noop);
+
+        assert_eq!(
+            matched_route(&router, "/product/42").as_deref(),
+            Some("/product/:id")
+        );
+    }
+
+    #[test]
+    fn captures_parameters() {
+        let params = params("/shop/:shop/product/:id", "/shop/books/product/42").unwrap();
+
+        assert_eq!(params.get("shop"), Some("books"));
+        assert_eq!(params.get("id"), Some("42"));
+        assert_eq!(params.get("name"), None);
+    }
+
+    #[test]
+    fn a_parameter_never_captures_an_empty_segment() {
+        assert!(params("/product/:id", "/product/").is_none());
+        assert!(params("/product/:id", "/product").is_none());
+        assert!(params("/product/:id/reviews", "/product//reviews").is_none());
+        assert!(params("/product/:id", "/product/42/reviews").is_none());
+    }
+
+    #[test]
+    fn a_trailing_wildcard_captures_the_remaining_segments() {
+        let params_of =
+            |path| params("/help/*rest", path).map(|params| params.get("rest").map(str::to_owned));
+
+        assert_eq!(
+            params_of("/help/billing/refunds"),
+            Some(Some("billing/refunds".to_owned()))
+        );
+        assert_eq!(params_of("/help/billing"), Some(Some("billing".to_owned())));
+        assert_eq!(params_of("/help"), Some(Some(String::new())));
+        assert_eq!(params_of("/helpdesk"), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid route /help/*rest/end")]
+    fn a_wildcard_must_be_the_last_segment() {
+        let _ = Router::new().add("/help/*rest/end", noop);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid route /shop/:id/product/:id")]
+    fn a_parameter_can_only_be_captured_once() {
+        let _ = Router::new().add("/shop/:id/product/:id", noop);
+    }
+
+    #[test]
+    fn parsing_a_parameter_fails_when_it_is_missing_or_invalid() {
+        let params = params("/product/:id", "/product/abc").unwrap();
+
+        assert_eq!(params.parse::<String>("id").unwrap(), "abc");
+        assert!(params.parse::<u32>("id").is_err());
+        assert!(params.parse::<u32>("page").is_err());
+    }
+}
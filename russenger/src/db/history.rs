@@ -0,0 +1,92 @@
+//! The `history` module keeps the last steps of every user, in the `russenger_history` table.
+//!
+//! A step is the path of an action along with the `Data` it received. A step is recorded every time an action of the
+//! router runs for a new path, and the `Data` of the last step is updated when the same action runs again. Only the
+//! last `MAX_HISTORY` steps of a user are kept.
+use serde::{Deserialize, Serialize};
+
+use super::{
+    sql::{upsert, Param},
+    timed, Query,
+};
+use crate::error::Result;
+use crate::response_models::data::Data;
+
+/// The number of steps kept for every user.
+pub(crate) const MAX_HISTORY: usize = 20;
+
+const CREATE_TABLE: &str = "CREATE TABLE IF NOT EXISTS russenger_history (
+    facebook_user_id VARCHAR(255) PRIMARY KEY NOT NULL,
+    steps TEXT NOT NULL
+)";
+
+const SELECT_STEPS: &str = "SELECT steps FROM russenger_history WHERE facebook_user_id = ?";
+
+/// A step of the history of a user.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct Step {
+    pub path: String,
+    pub data: Data,
+}
+
+impl Query {
+    /// Creates the `russenger_history` table, if it doesn't exist.
+    pub(crate) async fn migrate_history(&self) -> Result<()> {
+        self.execute(CREATE_TABLE, Vec::new()).await
+    }
+
+    async fn get_history(&self, user_id: &str) -> Result<Vec<Step>> {
+        let steps = self
+            .fetch_text(SELECT_STEPS, vec![Param::Text(user_id)])
+            .await?;
+        Ok(steps
+            .map(|steps| serde_json::from_str(&steps))
+            .transpose()?
+            .unwrap_or_default())
+    }
+
+    async fn set_history(&self, user_id: &str, steps: &[Step]) -> Result<()> {
+        let statement = upsert("russenger_history", &["facebook_user_id"], &["steps"]);
+        let steps = serde_json::to_string(steps)?;
+        let params = vec![Param::Text(user_id), Param::Text(&steps)];
+        self.execute(&statement, params).await
+    }
+
+    /// Records that the action of `path` runs with `data` for the user.
+    pub(crate) async fn push_history(&self, user_id: &str, path: &str, data: &Data) -> Result<()> {
+        timed("history_push", async {
+            let user_id = self.user_id(user_id);
+            let mut steps = self.get_history(&user_id).await?;
+            let step = Step {
+                path: path.to_owned(),
+                data: data.clone(),
+            };
+            match steps.last_mut() {
+                Some(last) if last.path == path => {
+                    if serde_json::to_string(&last.data)? == serde_json::to_string(data)? {
+                        return Ok(());
+                    }
+                    *last = step;
+                }
+                _ => steps.push(step),
+            }
+            let skip = steps.len().saturating_sub(MAX_HISTORY);
+            self.set_history(&user_id, &steps[skip..]).await
+        })
+        .await
+    }
+
+    /// Removes the current step of the user, and returns the previous one, which becomes the current step.
+    ///
+    /// Returns `None` if the user has no previous step.
+    pub(crate) async fn back(&self, user_id: &str) -> Result<Option<Step>> {
+        timed("history_back", async {
+            let user_id = self.user_id(user_id);
+            let mut steps = self.get_history(&user_id).await?;
+            steps.pop();
+            self.set_history(&user_id, &steps).await?;
+            Ok(steps.last().cloned())
+        })
+        .await
+    }
+}
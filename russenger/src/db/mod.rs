@@ -50,13 +50,21 @@
 //! }
 //! ```
 //!
+mod history;
 mod models;
+mod payload;
+mod session;
+mod sql;
 
 use models::RussengerUser;
 use rusql_alchemy::prelude::*;
-use std::sync::Arc;
+use std::{borrow::Cow, future::Future, sync::Arc, time::Instant};
 
 use crate::error::Result;
+use crate::metrics;
+
+pub use payload::{MAX_PAYLOAD_LENGTH, PAYLOAD_TTL};
+pub use session::{Session, SessionValue};
 
 /// The `Query` struct represents a database query.
 ///
@@ -66,6 +74,9 @@ use crate::error::Result;
 ///
 /// * `db`: The database connection. This is an instance of the `DB` enum.
 ///
+/// The users of a page registered with `App::page` are stored as `{page_id}:{user_id}` in `RussengerUser`, so that the
+/// same user has a separate path on every page. The users of the default page are stored with their id only.
+///
 /// # Methods
 ///
 /// * `new`: This method creates a new `Query`. It establishes a connection to the database and returns a `Query` with the established connection.
@@ -75,10 +86,39 @@ use crate::error::Result;
 #[derive(Clone)]
 pub struct Query {
     pub conn: Arc<Connection>,
+    pub(crate) page_id: Option<String>,
 }
 
 /// Represents a query object used for database operations.
 impl Query {
+    /// Returns a `Query` whose users are scoped to the page `page_id`.
+    pub(crate) fn for_page(&self, page_id: &str) -> Self {
+        Self {
+            conn: self.conn.clone(),
+            page_id: Some(page_id.to_owned()),
+        }
+    }
+
+    /// Returns the id under which `user_id` is stored in `RussengerUser`.
+    pub fn user_id<'a>(&self, user_id: &'a str) -> Cow<'a, str> {
+        match &self.page_id {
+            Some(page_id) => Cow::Owned(format!("{page_id}:{user_id}")),
+            None => Cow::Borrowed(user_id),
+        }
+    }
+
+    /// Checks that the database can be reached, by running `SELECT 1`.
+    pub async fn ping(&self) -> Result<()> {
+        timed("ping", async {
+            #[cfg(not(feature = "turso"))]
+            sqlx::query("SELECT 1").execute(&*self.conn).await?;
+            #[cfg(feature = "turso")]
+            self.conn.query("SELECT 1", ()).await?;
+            Ok(())
+        })
+        .await
+    }
+
     /// Creates a new record in the database.
     ///
     /// # Arguments
@@ -89,13 +129,17 @@ impl Query {
     ///
     /// Returns `true` if the record is successfully created, `false` otherwise.
     pub async fn create(&self, user_id: &str) -> Result<()> {
-        if RussengerUser::get(kwargs!(facebook_user_id == user_id), &self.conn)
-            .await?
-            .is_none()
-        {
-            return RussengerUser::create(kwargs!(facebook_user_id = user_id), &self.conn).await;
-        }
-        Ok(())
+        timed("create", async {
+            let user_id = self.user_id(user_id);
+            if RussengerUser::get(kwargs!(facebook_user_id == user_id), &self.conn)
+                .await?
+                .is_none()
+            {
+                return RussengerUser::create(kwargs!(facebook_user_id = user_id), &self.conn).await;
+            }
+            Ok(())
+        })
+        .await
     }
 
     /// Sets the action for a user.
@@ -143,11 +187,17 @@ impl Query {
     /// }
     /// ```
     pub(crate) async fn set_path(&self, user_id: &str, path: &str) -> Result<()> {
-        let mut user = RussengerUser::get(kwargs!(facebook_user_id == user_id), &self.conn)
-            .await?
-            .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, "user not found"))?;
-        user.action_path = path.to_owned();
-        user.update(&self.conn).await
+        timed("set_path", async {
+            let user_id = self.user_id(user_id);
+            let mut user = RussengerUser::get(kwargs!(facebook_user_id == user_id), &self.conn)
+                .await?
+                .ok_or_else(|| {
+                    std::io::Error::new(std::io::ErrorKind::NotFound, "user not found")
+                })?;
+            user.action_path = path.to_owned();
+            user.update(&self.conn).await
+        })
+        .await
     }
 
     /// Retrieves the action for a user.
@@ -160,10 +210,22 @@ impl Query {
     ///
     /// Returns the action as an `Option<String>`. Returns `None` if the user is not found.
     pub async fn get_path(&self, user_id: &str) -> Result<Option<String>> {
-        Ok(
-            RussengerUser::get(kwargs!(facebook_user_id == user_id), &self.conn)
-                .await?
-                .map(|user| user.action_path),
-        )
+        timed("get_path", async {
+            let user_id = self.user_id(user_id);
+            Ok(
+                RussengerUser::get(kwargs!(facebook_user_id == user_id), &self.conn)
+                    .await?
+                    .map(|user| user.action_path),
+            )
+        })
+        .await
     }
 }
+
+/// Runs `query`, recording its duration and outcome under `operation`.
+async fn timed<T>(operation: &str, query: impl Future<Output = Result<T>>) -> Result<T> {
+    let start = Instant::now();
+    let result = query.await;
+    metrics::db_query(operation, start.elapsed(), result.is_ok());
+    result
+}
@@ -0,0 +1,85 @@
+//! The `payload` module stores the `Data` of the payloads that are too long for Facebook, in the `russenger_payload`
+//! table.
+//!
+//! The payloads of the quick replies and of the postback buttons are limited to `MAX_PAYLOAD_LENGTH` characters. When a
+//! response holds a longer payload, `Res::send` stores its `Data` under a short random key and sends a payload that only
+//! carries the path and the key. The `Data` is loaded back when the user taps the button, until it expires after
+//! `PAYLOAD_TTL`.
+//!
+//! The keys are drawn from the random number generator of the operating system, since anyone holding a key can load
+//! its `Data` when the payloads are not signed.
+use std::time::Duration;
+
+use super::{
+    sql::{now, Param},
+    timed, Query,
+};
+use crate::error::Result;
+use crate::response_models::data::Data;
+
+/// The maximum length of the payloads sent to Facebook.
+pub const MAX_PAYLOAD_LENGTH: usize = 1000;
+
+/// How long the stored `Data` of a payload is kept.
+pub const PAYLOAD_TTL: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+const CREATE_TABLE: &str = "CREATE TABLE IF NOT EXISTS russenger_payload (
+    payload_key VARCHAR(64) PRIMARY KEY NOT NULL,
+    data TEXT NOT NULL,
+    expires_at BIGINT NOT NULL
+)";
+
+const SELECT_DATA: &str =
+    "SELECT data FROM russenger_payload WHERE payload_key = ? AND expires_at > ?";
+
+const INSERT_DATA: &str =
+    "INSERT INTO russenger_payload (payload_key, data, expires_at) VALUES (?, ?, ?)";
+
+const DELETE_EXPIRED: &str = "DELETE FROM russenger_payload WHERE expires_at <= ?";
+
+impl Query {
+    /// Creates the `russenger_payload` table, if it doesn't exist.
+    pub(crate) async fn migrate_payloads(&self) -> Result<()> {
+        self.execute(CREATE_TABLE, Vec::new()).await
+    }
+
+    /// Stores `data` and returns the key it was stored under.
+    pub(crate) async fn store_payload(&self, data: &Data) -> Result<String> {
+        timed("payload_store", async {
+            let mut key = [0; 16];
+            getrandom::fill(&mut key)?;
+            let key = hex::encode(key);
+            let data = serde_json::to_string(data)?;
+            let ttl = PAYLOAD_TTL.as_secs() as i64;
+            let params = vec![
+                Param::Text(&key),
+                Param::Text(&data),
+                Param::Integer(Some(now() + ttl)),
+            ];
+            self.execute(INSERT_DATA, params).await?;
+            Ok(key)
+        })
+        .await
+    }
+
+    /// Returns the `Data` stored under `key`, or `None` if there is none or if it expired.
+    pub(crate) async fn load_payload(&self, key: &str) -> Result<Option<Data>> {
+        timed("payload_load", async {
+            let params = vec![Param::Text(key), Param::Integer(Some(now()))];
+            let data = self.fetch_text(SELECT_DATA, params).await?;
+            Ok(data.map(|data| serde_json::from_str(&data)).transpose()?)
+        })
+        .await
+    }
+
+    /// Deletes the expired `Data` of the payloads.
+    ///
+    /// This runs every hour while the workers of the `App` are running.
+    pub async fn delete_expired_payloads(&self) -> Result<()> {
+        timed("payload_delete_expired", async {
+            self.execute(DELETE_EXPIRED, vec![Param::Integer(Some(now()))])
+                .await
+        })
+        .await
+    }
+}
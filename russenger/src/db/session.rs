@@ -0,0 +1,319 @@
+//! The `session` module stores values for a user between their actions, in the `russenger_session` table.
+//!
+//! The values are serialized to JSON and kept for every user, on every page, until they are removed or until they
+//! expire. This avoids defining a model only to remember a few answers between the steps of a conversation.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use russenger::prelude::*;
+//!
+//! async fn index(res: Res, req: Req) -> Result<()> {
+//!     res.send(TextModel::new(&req.user, "What is your name?")).await?;
+//!     res.redirect("/get_name").await?;
+//!     Ok(())
+//! }
+//!
+//! async fn get_name(res: Res, req: Req) -> Result<()> {
+//!     let name: String = req.data.get_value()?;
+//!     req.session().set("name", &name).await?;
+//!     res.send(TextModel::new(&req.user, "How old are you?")).await?;
+//!     res.redirect("/get_age").await?;
+//!     Ok(())
+//! }
+//!
+//! async fn get_age(res: Res, req: Req) -> Result<()> {
+//!     let age: u32 = req.data.get_value::<String>()?.trim().parse()?;
+//!     let name: String = req.session().get("name").await?.unwrap_or_default();
+//!     res.send(TextModel::new(&req.user, format!("{name} is {age} years old"))).await?;
+//!     req.session().remove("name").await?;
+//!     Ok(())
+//! }
+//!
+//! let router = router![("/", index), ("/get_name", get_name), ("/get_age", get_age)];
+//! ```
+//!
+//! A type implementing [`SessionValue`] is stored under its own key, and an `#[action]` can take it as a
+//! `Session<T>` argument, see [`SessionValue`].
+use std::{
+    ops::{Deref, DerefMut},
+    sync::Arc,
+    time::Duration,
+};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::{
+    sql::{now, upsert, Param},
+    timed, Query,
+};
+use crate::error::Result;
+
+const CREATE_TABLE: &str = "CREATE TABLE IF NOT EXISTS russenger_session (
+    facebook_user_id VARCHAR(255) NOT NULL,
+    session_key VARCHAR(255) NOT NULL,
+    value TEXT NOT NULL,
+    expires_at BIGINT,
+    PRIMARY KEY (facebook_user_id, session_key)
+)";
+
+const SELECT_VALUE: &str = "SELECT value FROM russenger_session
+    WHERE facebook_user_id = ? AND session_key = ? AND (expires_at IS NULL OR expires_at > ?)";
+
+const DELETE_EXPIRED_VALUES: &str =
+    "DELETE FROM russenger_session WHERE facebook_user_id = ? AND expires_at <= ?";
+
+const DELETE_KEY: &str =
+    "DELETE FROM russenger_session WHERE facebook_user_id = ? AND session_key = ?";
+
+const DELETE_USER: &str = "DELETE FROM russenger_session WHERE facebook_user_id = ?";
+
+const DELETE_EXPIRED: &str =
+    "DELETE FROM russenger_session WHERE expires_at IS NOT NULL AND expires_at <= ?";
+
+/// The session of a user, returned by `Req::session`.
+///
+/// The values are scoped to the user and to the page they wrote to.
+///
+/// `Session` stores values of any type under any key. `Session<T>` holds the [`SessionValue`] `T`, loaded from the
+/// session of the user, and saves it back with [`Session::save`].
+#[derive(Clone)]
+pub struct Session<T = ()> {
+    query: Arc<Query>,
+    user: String,
+    value: T,
+}
+
+/// `SessionValue` is implemented by the types kept in the session under their own key.
+///
+/// An `#[action]` can take a `Session<T>` argument: the value stored under `T::KEY` is loaded before the action
+/// runs, or `T::default()` if there is none. The changes are only stored when `save` is called.
+///
+/// # Examples
+///
+/// ```rust
+/// use russenger::prelude::*;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Default, Serialize, Deserialize)]
+/// struct Cart {
+///     products: Vec<String>,
+/// }
+///
+/// impl SessionValue for Cart {
+///     const KEY: &'static str = "cart";
+/// }
+///
+/// #[action]
+/// async fn add_to_cart(res: Res, req: Req, Text(product): Text, mut cart: Session<Cart>) -> Result<()> {
+///     cart.products.push(product);
+///     cart.save().await?;
+///     let message = format!("{} products in your cart", cart.products.len());
+///     res.send(TextModel::new(&req.user, message)).await?;
+///     Ok(())
+/// }
+/// ```
+pub trait SessionValue: Serialize + DeserializeOwned + Default + Send + Sync {
+    /// The key the value is stored under.
+    const KEY: &'static str;
+}
+
+impl Session {
+    pub(crate) fn new(query: Arc<Query>, user: &str) -> Self {
+        Self {
+            query,
+            user: user.to_owned(),
+            value: (),
+        }
+    }
+
+    /// Loads the value stored under `T::KEY`, or `T::default()` if there is none or if it expired.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the query fails or if the value can't be deserialized into `T`.
+    pub async fn load<T: SessionValue>(self) -> Result<Session<T>> {
+        let value = self.get(T::KEY).await?.unwrap_or_default();
+        Ok(Session {
+            query: self.query,
+            user: self.user,
+            value,
+        })
+    }
+
+    /// Returns the value stored under `key`, or `None` if there is none or if it expired.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the query fails or if the value can't be deserialized into `T`.
+    pub async fn get<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>> {
+        let value = self.query.get_session_value(&self.user, key).await?;
+        Ok(value
+            .map(|value| serde_json::from_str(&value))
+            .transpose()?)
+    }
+
+    /// Stores `value` under `key`, replacing the previous value.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the value can't be serialized or if the query fails.
+    pub async fn set<T: Serialize>(&self, key: &str, value: &T) -> Result<()> {
+        let value = serde_json::to_string(value)?;
+        self.query
+            .set_session_value(&self.user, key, &value, None)
+            .await
+    }
+
+    /// Stores `value` under `key` for the duration `ttl`, replacing the previous value.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the value can't be serialized or if the query fails.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::time::Duration;
+    /// use russenger::prelude::*;
+    ///
+    /// async fn send_code(res: Res, req: Req) -> Result<()> {
+    ///     let code = 123456;
+    ///     req.session().set_with_ttl("code", &code, Duration::from_secs(300)).await?;
+    ///     res.send(TextModel::new(&req.user, "Enter the code we sent you, it is valid for 5 minutes")).await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn set_with_ttl<T: Serialize>(
+        &self,
+        key: &str,
+        value: &T,
+        ttl: Duration,
+    ) -> Result<()> {
+        let value = serde_json::to_string(value)?;
+        let expires_at = now().saturating_add(ttl.as_secs().try_into().unwrap_or(i64::MAX));
+        self.query
+            .set_session_value(&self.user, key, &value, Some(expires_at))
+            .await
+    }
+
+    /// Removes the value stored under `key`.
+    pub async fn remove(&self, key: &str) -> Result<()> {
+        self.query.remove_session_value(&self.user, key).await
+    }
+
+    /// Removes every value of the session.
+    pub async fn clear(&self) -> Result<()> {
+        self.query.clear_session(&self.user).await
+    }
+}
+
+impl<T: SessionValue> Session<T> {
+    /// Stores the value under `T::KEY`, replacing the previous value.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the value can't be serialized or if the query fails.
+    pub async fn save(&self) -> Result<()> {
+        let value = serde_json::to_string(&self.value)?;
+        self.query
+            .set_session_value(&self.user, T::KEY, &value, None)
+            .await
+    }
+
+    /// Returns the value, without saving it.
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<T: SessionValue> Deref for Session<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T: SessionValue> DerefMut for Session<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
+
+impl Query {
+    /// Creates the `russenger_session` table, if it doesn't exist.
+    pub(crate) async fn migrate_sessions(&self) -> Result<()> {
+        self.execute(CREATE_TABLE, Vec::new()).await
+    }
+
+    async fn get_session_value(&self, user_id: &str, key: &str) -> Result<Option<String>> {
+        timed("session_get", async {
+            let user_id = self.user_id(user_id);
+            let params = vec![
+                Param::Text(&user_id),
+                Param::Text(key),
+                Param::Integer(Some(now())),
+            ];
+            self.fetch_text(SELECT_VALUE, params).await
+        })
+        .await
+    }
+
+    async fn set_session_value(
+        &self,
+        user_id: &str,
+        key: &str,
+        value: &str,
+        expires_at: Option<i64>,
+    ) -> Result<()> {
+        timed("session_set", async {
+            let user_id = self.user_id(user_id);
+            let params = vec![Param::Text(&user_id), Param::Integer(Some(now()))];
+            self.execute(DELETE_EXPIRED_VALUES, params).await?;
+            let statement = upsert(
+                "russenger_session",
+                &["facebook_user_id", "session_key"],
+                &["value", "expires_at"],
+            );
+            let params = vec![
+                Param::Text(&user_id),
+                Param::Text(key),
+                Param::Text(value),
+                Param::Integer(expires_at),
+            ];
+            self.execute(&statement, params).await
+        })
+        .await
+    }
+
+    async fn remove_session_value(&self, user_id: &str, key: &str) -> Result<()> {
+        timed("session_remove", async {
+            let user_id = self.user_id(user_id);
+            let params = vec![Param::Text(&user_id), Param::Text(key)];
+            self.execute(DELETE_KEY, params).await
+        })
+        .await
+    }
+
+    async fn clear_session(&self, user_id: &str) -> Result<()> {
+        timed("session_clear", async {
+            let user_id = self.user_id(user_id);
+            self.execute(DELETE_USER, vec![Param::Text(&user_id)]).await
+        })
+        .await
+    }
+
+    /// Deletes the expired session values of every user.
+    ///
+    /// The expired values are never returned, and the expired values of a user are deleted whenever a value is set for
+    /// them. This also runs every hour while the workers of the `App` are running, to reclaim the space of the users who
+    /// don't come back.
+    pub async fn delete_expired_sessions(&self) -> Result<()> {
+        timed("session_delete_expired", async {
+            self.execute(DELETE_EXPIRED, vec![Param::Integer(Some(now()))])
+                .await
+        })
+        .await
+    }
+}
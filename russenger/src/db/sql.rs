@@ -0,0 +1,148 @@
+//! The `sql` module runs the queries of the tables that are not defined with a rusql-alchemy model, on every backend.
+use std::{
+    borrow::Cow,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use super::Query;
+use crate::error::Result;
+
+/// The current time, in seconds since the Unix epoch.
+pub(super) fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs() as i64)
+}
+
+/// A parameter of a query.
+pub(super) enum Param<'a> {
+    Text(&'a str),
+    Integer(Option<i64>),
+}
+
+/// Returns the statement inserting a row into `table`, or updating its `columns` if a row with the same `keys` exists.
+///
+/// Writing a row with a single statement keeps it from being lost when two workers write it at the same time, or when
+/// the application stops between a `DELETE` and an `INSERT`.
+pub(super) fn upsert(table: &str, keys: &[&str], columns: &[&str]) -> String {
+    let names: Vec<&str> = keys.iter().chain(columns).copied().collect();
+    let placeholders = vec!["?"; names.len()].join(", ");
+    let insert = format!(
+        "INSERT INTO {table} ({}) VALUES ({placeholders})",
+        names.join(", ")
+    );
+    #[cfg(feature = "mysql")]
+    {
+        let updates: Vec<String> = columns
+            .iter()
+            .map(|column| format!("{column} = VALUES({column})"))
+            .collect();
+        format!("{insert} ON DUPLICATE KEY UPDATE {}", updates.join(", "))
+    }
+    #[cfg(not(feature = "mysql"))]
+    {
+        let updates: Vec<String> = columns
+            .iter()
+            .map(|column| format!("{column} = excluded.{column}"))
+            .collect();
+        format!(
+            "{insert} ON CONFLICT ({}) DO UPDATE SET {}",
+            keys.join(", "),
+            updates.join(", ")
+        )
+    }
+}
+
+/// Rewrites the `?` placeholders of `statement` for the database in use.
+fn sql(statement: &str) -> Cow<'_, str> {
+    #[cfg(feature = "postgres")]
+    {
+        let mut index = 0;
+        let statement = statement
+            .split('?')
+            .enumerate()
+            .map(|(position, part)| {
+                if position == 0 {
+                    part.to_owned()
+                } else {
+                    index += 1;
+                    format!("${index}{part}")
+                }
+            })
+            .collect();
+        Cow::Owned(statement)
+    }
+    #[cfg(not(feature = "postgres"))]
+    Cow::Borrowed(statement)
+}
+
+impl Query {
+    #[cfg(not(feature = "turso"))]
+    pub(super) async fn execute(&self, statement: &str, params: Vec<Param<'_>>) -> Result<()> {
+        let statement = sql(statement);
+        let mut query = sqlx::query(&statement);
+        for param in params {
+            query = match param {
+                Param::Text(value) => query.bind(value),
+                Param::Integer(value) => query.bind(value),
+            };
+        }
+        query.execute(&*self.conn).await?;
+        Ok(())
+    }
+
+    #[cfg(not(feature = "turso"))]
+    pub(super) async fn fetch_text(
+        &self,
+        statement: &str,
+        params: Vec<Param<'_>>,
+    ) -> Result<Option<String>> {
+        let statement = sql(statement);
+        let mut query = sqlx::query_scalar(&statement);
+        for param in params {
+            query = match param {
+                Param::Text(value) => query.bind(value),
+                Param::Integer(value) => query.bind(value),
+            };
+        }
+        Ok(query.fetch_optional(&*self.conn).await?)
+    }
+
+    #[cfg(feature = "turso")]
+    pub(super) async fn execute(&self, statement: &str, params: Vec<Param<'_>>) -> Result<()> {
+        self.conn
+            .execute(&sql(statement), turso_params(params))
+            .await?;
+        Ok(())
+    }
+
+    #[cfg(feature = "turso")]
+    pub(super) async fn fetch_text(
+        &self,
+        statement: &str,
+        params: Vec<Param<'_>>,
+    ) -> Result<Option<String>> {
+        let mut rows = self
+            .conn
+            .query(&sql(statement), turso_params(params))
+            .await?;
+        match rows.next().await? {
+            Some(row) => Ok(Some(row.get::<String>(0)?)),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(feature = "turso")]
+fn turso_params(params: Vec<Param<'_>>) -> Vec<rusql_alchemy::libsql::Value> {
+    use rusql_alchemy::libsql::Value;
+
+    params
+        .into_iter()
+        .map(|param| match param {
+            Param::Text(value) => Value::Text(value.to_owned()),
+            Param::Integer(Some(value)) => Value::Integer(value),
+            Param::Integer(None) => Value::Null,
+        })
+        .collect()
+}
@@ -5,15 +5,22 @@
 //! ## Modules
 //!
 //! - `cli`: This module provides command-line interface utilities.
+//! - `config`: This module contains `AppConfig`, the settings used to create an `App`.
 //! - `core`: This module contains the core functionalities of the Russenger bot framework.
+//! - `metrics`: This module records Prometheus metrics, served at `/metrics` behind the `metrics` feature.
 //! - `prelude`: This module re-exports important traits and structs for convenience.
 //! - `query`: This module provides utilities for handling queries.
 //! - `response_models`: This module contains models for different types of responses.
+//! - `telemetry`: This module installs a `tracing` subscriber, exporting the spans with OpenTelemetry behind the `opentelemetry` feature.
+//!   It is only available with the `telemetry` feature, which is enabled by default.
+//! - `testing`: This module provides utilities to test a bot without Facebook, behind the `testing` feature.
 //!
 //! ## Macros
 //!
-//! - `action`: This proc macro is used to define an action.
-//! - `russenger::actions!`: This macro is used to register the actions for the main application.
+//! - `action`: This attribute macro defines an action whose arguments are extracted from the request, see
+//!   [`core::extract`].
+//! - `RussengerPayload`: This derive macro turns an enum into typed payloads, see `Router::payload`.
+//! - `router!`: This macro builds a `Router` from a list of paths and actions.
 //!
 //! ## New Features
 //!
@@ -127,11 +134,17 @@
 //! ```
 //!
 //! These examples demonstrate how to define an action, use custom models, and register actions for the main application.
+pub mod config;
 pub mod core;
 pub mod db;
+pub mod metrics;
 pub mod prelude;
 pub mod response_models;
 pub mod services;
+#[cfg(feature = "telemetry")]
+pub mod telemetry;
+#[cfg(feature = "testing")]
+pub mod testing;
 pub mod error {
     pub type Error = Box<dyn std::error::Error + Send + Sync>;
     pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -139,34 +152,35 @@ pub mod error {
 
 use crate::db::Query;
 use actix_files as fs;
-use actix_web::{web, App as ActixApp, HttpServer};
-pub use core::router::Router;
+use actix_web::{web, App as ActixApp, HttpServer, Scope};
+pub use config::AppConfig;
+use config::{ConfigError, PageConfig};
+use core::{
+    graph::{GraphClient, RetryPolicy},
+    request::Req,
+    response::Res,
+};
+pub use core::{middleware::Middleware, router::Router};
 use error::Result;
 pub use rusql_alchemy::{self, Database};
-use std::{collections::HashSet, sync::Arc};
-use tokio::sync::Mutex;
+pub use russenger_macros::action;
+use services::{
+    health::TokenChecks,
+    queue::{OverflowPolicy, UserQueues},
+    worker::{Dispatcher, WorkerPool, DEFAULT_WORKERS},
+};
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 
-#[derive(Clone, Default)]
-struct ActionLock {
-    locked_users: Arc<Mutex<HashSet<String>>>,
-}
-
-impl ActionLock {
-    async fn lock(&self, user: &str) -> bool {
-        let mut locked_user = self.locked_users.lock().await;
-        if !locked_user.contains(user) {
-            locked_user.insert(user.to_string());
-            true
-        } else {
-            false
-        }
-    }
-
-    async fn unlock(&self, user: &str) {
-        let mut locked_users = self.locked_users.lock().await;
-        locked_users.remove(user);
-    }
-}
+/// The default time a graceful shutdown waits for the open requests, then for the running actions.
+pub const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(30);
 
 /// # App State
 ///
@@ -179,52 +193,114 @@ impl ActionLock {
 pub struct App {
     query: Arc<Query>,
     router: Arc<Router>,
-    action_lock: ActionLock,
-    facebook_api_version: String,
-    page_access_token: String,
+    user_queues: UserQueues,
+    workers: usize,
+    dispatcher: Option<Dispatcher>,
+    graph: GraphClient,
+    pages: HashMap<String, PageConfig>,
+    app_secret: Option<String>,
+    verify_signature: bool,
+    payload_secret: Option<Arc<str>>,
+    verify_token: Option<String>,
+    static_files: Option<(String, String)>,
+    token_checks: TokenChecks,
+    shutting_down: Arc<AtomicBool>,
+    shutdown_timeout: Duration,
+    hash_user_ids: bool,
     addr: (String, u16),
 }
 
 impl App {
     /// `init` is method to create new `App` instance. in russenger
+    ///
+    /// The settings are read from the environment, see [`AppConfig::from_env`].
     pub async fn init() -> Result<Self> {
-        let facebook_api_version = std::env::var("FACEBOOK_API_VERSION").unwrap_or("v19.0".into());
-        let page_access_token = std::env::var("PAGE_ACCESS_TOKEN")?;
+        Self::with_config(AppConfig::from_env()?).await
+    }
 
-        let host = std::env::var("HOST").unwrap_or_else(|_| "0.0.0.0".into());
-        let port = std::env::var("PORT")
-            .ok()
-            .and_then(|p| p.parse().ok())
-            .unwrap_or(2453);
+    /// `with_config` creates a new `App` instance from an [`AppConfig`].
+    ///
+    /// The configuration is validated before the database is opened, so a missing setting fails at startup.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use russenger::prelude::*;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<()> {
+    ///     let config = AppConfig::from_env()?.port(8080);
+    ///     App::with_config(config).await?
+    ///         .attach(router![("/", |res: Res, req: Req| async move {
+    ///             res.send(TextModel::new(&req.user, "Hello")).await?;
+    ///             Ok(())
+    ///         })])
+    ///         .launch()
+    ///         .await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn with_config(config: AppConfig) -> Result<Self> {
+        config.validate()?;
 
         let database = {
             #[cfg(not(feature = "turso"))]
             {
-                let database_url = std::env::var("DATABASE_URL")?;
-                Database::new(&database_url).await?
+                Database::new(&config.database_url).await?
             }
             #[cfg(feature = "turso")]
             {
-                let path = std::env::var("DB_PATH")?;
-                let turso_db_url = std::env::var("TURSO_DB_URL")?;
-                let turso_auth_token = std::env::var("TURSO_AUTH_TOKEN")?;
-                Database::new_remote_replicate(&path, &turso_db_url, &turso_auth_token).await?
+                Database::new_remote_replicate(
+                    &config.db_path,
+                    &config.turso_db_url,
+                    &config.turso_auth_token,
+                )
+                .await?
             }
         };
-        
+        let graph = GraphClient::new(&config.facebook_api_version, &config.page_access_token)
+            .base_url(&config.graph_api_url);
+
+        Ok(Self {
+            app_secret: config.app_secret,
+            payload_secret: config.payload_secret.map(Into::into),
+            pages: config.pages,
+            verify_token: Some(config.verify_token),
+            addr: (config.host, config.port),
+            ..Self::from_database(database, graph).await?
+        })
+    }
+
+    /// Creates an `App` from an opened database, which is migrated, and a `GraphClient`.
+    pub(crate) async fn from_database(database: Database, graph: GraphClient) -> Result<Self> {
         database.migrate().await?;
 
         let query = Query {
             conn: Arc::new(database.conn),
+            page_id: None,
         };
+        query.migrate_sessions().await?;
+        query.migrate_history().await?;
+        query.migrate_payloads().await?;
 
         Ok(Self {
             query: query.into(),
             router: Arc::new(Router::new()),
-            action_lock: ActionLock::default(),
-            facebook_api_version,
-            page_access_token,
-            addr: (host, port),
+            user_queues: UserQueues::default(),
+            workers: DEFAULT_WORKERS,
+            dispatcher: None,
+            graph,
+            pages: HashMap::new(),
+            app_secret: None,
+            verify_signature: true,
+            payload_secret: None,
+            verify_token: None,
+            static_files: Some(("/static".into(), "static".into())),
+            token_checks: TokenChecks::default(),
+            shutting_down: Arc::default(),
+            shutdown_timeout: DEFAULT_SHUTDOWN_TIMEOUT,
+            hash_user_ids: false,
+            addr: ("0.0.0.0".into(), 2453),
         })
     }
 
@@ -266,41 +342,512 @@ impl App {
     /// ```
     ///
     /// This method is useful for organizing your application's routes into modular and reusable groups.
-    pub fn attach(mut self, router: Router) -> Self {
+    ///
+    /// # Panics
+    ///
+    /// Only one of the attached routers can handle the unknown paths: this method panics if `router` sets a
+    /// `fallback` or `reset_unknown_path` and a router attached before already did.
+    pub fn attach(mut self, mut router: Router) -> Self {
+        router.apply_middlewares();
+        let app_router = Arc::get_mut(&mut self.router).expect("Router already shared");
+        app_router.routes.extend(router.routes);
+        app_router.patterns.extend(router.patterns);
+        if router.fallback.is_some() || router.reset_unknown_path {
+            if app_router.fallback.is_some() || app_router.reset_unknown_path {
+                panic!("Two attached routers set a fallback or reset_unknown_path, only one can handle the unknown paths");
+            }
+            app_router.fallback = router.fallback;
+            app_router.reset_unknown_path = router.reset_unknown_path;
+        }
+        self
+    }
+
+    /// `middleware` adds a middleware that runs around every action of the application.
+    ///
+    /// Global middlewares run before the middlewares of the attached routers. See the [`middleware`](core::middleware) module.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use russenger::prelude::*;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<()> {
+    ///     App::init().await?
+    ///         .middleware(Logger)
+    ///         .middleware(TypingIndicator)
+    ///         .attach(router![("/", |res: Res, req: Req| async move {
+    ///             res.send(TextModel::new(&req.user, "Hello")).await?;
+    ///             Ok(())
+    ///         })])
+    ///         .launch()
+    ///         .await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn middleware(mut self, middleware: impl Middleware) -> Self {
+        Arc::get_mut(&mut self.router)
+            .expect("Router already shared")
+            .middlewares
+            .push(Arc::new(middleware));
+        self
+    }
+
+    /// `on_error` sets the handler called when an action fails.
+    ///
+    /// The handler receives the `Res` and the `Req` of the failing action, its path and the error, which includes the
+    /// errors of `Res::send` propagated with `?`. It can send an apology to the user, redirect them or report the error
+    /// elsewhere. Without a handler, errors are only logged with `tracing`, see the `telemetry` module.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use russenger::prelude::*;
+    ///
+    /// async fn on_error(res: Res, req: Req, path: String, err: error::Error) -> Result<()> {
+    ///     eprintln!("Action {path} failed: {err}");
+    ///     res.send(TextModel::new(&req.user, "Sorry, something went wrong")).await?;
+    ///     res.redirect("/").await?;
+    ///     Ok(())
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<()> {
+    ///     App::init().await?
+    ///         .on_error(on_error)
+    ///         .attach(router![("/", |res: Res, req: Req| async move {
+    ///             res.send(TextModel::new(&req.user, "Hello")).await?;
+    ///             Ok(())
+    ///         })])
+    ///         .launch()
+    ///         .await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn on_error<F, Fut>(mut self, handler: F) -> Self
+    where
+        F: Fn(Res, Req, String, error::Error) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        Arc::get_mut(&mut self.router)
+            .expect("Router already shared")
+            .error_handler = Some(Arc::new(move |res, req, path, err| {
+            Box::pin(handler(res, req, path, err))
+        }));
+        self
+    }
+
+    /// `on_rejected_payload` sets the handler run when the payload of a quick reply or of a postback is rejected.
+    ///
+    /// A payload is rejected when it expired, when the `Data` stored for a long payload expired, or when `sign_payloads`
+    /// is set and its signature is missing or invalid.
+    /// The handler receives the path named by the payload and the [`PayloadError`](response_models::payload::PayloadError).
+    /// Without a handler, the rejected payloads are only logged.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use russenger::prelude::*;
+    ///
+    /// async fn on_rejected(res: Res, req: Req, path: String, err: error::Error) -> Result<()> {
+    ///     let message = match err.downcast_ref::<PayloadError>() {
+    ///         Some(PayloadError::Expired) => "This offer is not available anymore",
+    ///         _ => "Sorry, this button can't be used",
+    ///     };
+    ///     res.send(TextModel::new(&req.user, message)).await?;
+    ///     Ok(())
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<()> {
+    ///     App::init().await?
+    ///         .sign_payloads("my_payload_secret")
+    ///         .on_rejected_payload(on_rejected)
+    ///         .attach(router![("/", |res: Res, req: Req| async move {
+    ///             res.send(TextModel::new(&req.user, "Hello")).await?;
+    ///             Ok(())
+    ///         })])
+    ///         .launch()
+    ///         .await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn on_rejected_payload<F, Fut>(mut self, handler: F) -> Self
+    where
+        F: Fn(Res, Req, String, error::Error) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
         Arc::get_mut(&mut self.router)
             .expect("Router already shared")
-            .routes
-            .extend(router.routes);
+            .rejection_handler = Some(Arc::new(move |res, req, path, err| {
+            Box::pin(handler(res, req, path, err))
+        }));
+        self
+    }
+
+    /// `queue_capacity` sets how many events can wait in the queue of a single user while one of their actions is running.
+    ///
+    /// The default capacity is `100`. When the queue is full, the `OverflowPolicy` decides which event is discarded.
+    pub fn queue_capacity(mut self, capacity: usize) -> Self {
+        self.user_queues.capacity = capacity;
+        self
+    }
+
+    /// `overflow_policy` sets what to do with a new event when the queue of its user is full.
+    ///
+    /// See [`OverflowPolicy`] for the available policies, the default is `OverflowPolicy::DropNewest`.
+    pub fn overflow_policy(mut self, overflow_policy: OverflowPolicy) -> Self {
+        self.user_queues.overflow_policy = overflow_policy;
+        self
+    }
+
+    /// `workers` sets the number of background workers running the actions.
+    ///
+    /// The webhook endpoint acknowledges the events as soon as they are queued, and the workers process them afterward.
+    /// Events of the same user are always processed in order, so the workers only run actions of different users in parallel.
+    /// The default is `4` workers.
+    pub fn workers(mut self, workers: usize) -> Self {
+        self.workers = workers;
+        self
+    }
+
+    /// `graph_api_url` sets the base URL of the Graph API, `https://graph.facebook.com` by default.
+    ///
+    /// It can also be set with the `GRAPH_API_URL` environment variable. This allows sending the requests through a
+    /// proxy, or to a mock server in integration tests.
+    pub fn graph_api_url(mut self, url: &str) -> Self {
+        self.graph = self.graph.base_url(url);
+        self
+    }
+
+    /// `request_timeout` sets the timeout of the requests sent to the Graph API.
+    ///
+    /// Every `Res` shares the same pooled HTTP client, so connections are reused between messages. The default timeout is 30 seconds.
+    pub fn request_timeout(mut self, timeout: Duration) -> Self {
+        self.graph = self.graph.timeout(timeout);
+        self
+    }
+
+    /// `retry_policy` sets how the requests to the Graph API are retried when they are rate limited or fail with a server error.
+    ///
+    /// By default, a request is retried 3 times with an exponential backoff. See [`RetryPolicy`](core::graph::RetryPolicy).
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use russenger::prelude::*;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<()> {
+    ///     App::init().await?
+    ///         .request_timeout(std::time::Duration::from_secs(10))
+    ///         .retry_policy(RetryPolicy::none())
+    ///         .attach(router![("/", |res: Res, req: Req| async move {
+    ///             res.send(TextModel::new(&req.user, "Hello")).await?;
+    ///             Ok(())
+    ///         })])
+    ///         .launch()
+    ///         .await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.graph = self.graph.retry_policy(retry_policy);
+        self
+    }
+
+    /// `page` adds a Facebook page served by the application.
+    ///
+    /// The webhook events are routed by the page id of their entry. Responses to the users of `page_id` are sent with
+    /// `page_access_token`, and the users and their paths are kept apart from the other pages. Events of a page that is
+    /// not registered are handled as events of the default page, configured with `PAGE_ACCESS_TOKEN`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use russenger::prelude::*;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<()> {
+    ///     App::init().await?
+    ///         .page("1234567890", "page_access_token_of_the_shop")
+    ///         .page("0987654321", "page_access_token_of_the_blog")
+    ///         .attach(router![("/", |res: Res, req: Req| async move {
+    ///             res.send(TextModel::new(&req.user, format!("Hello from {}", req.page_id))).await?;
+    ///             Ok(())
+    ///         })])
+    ///         .launch()
+    ///         .await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn page(mut self, page_id: &str, page_access_token: &str) -> Self {
+        self.pages
+            .insert(page_id.to_owned(), PageConfig::new(page_access_token));
+        self
+    }
+
+    /// `app_secret` sets the App Secret used to verify the `X-Hub-Signature-256` header of incoming webhook events.
+    ///
+    /// By default the secret is read from the `APP_SECRET` environment variable. Requests with a missing or invalid
+    /// signature are rejected with `403 Forbidden`. Without an App Secret, `launch` fails, unless
+    /// [`disable_signature_verification`](App::disable_signature_verification) was called.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use russenger::prelude::*;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<()> {
+    ///     App::init().await?
+    ///         .app_secret("my_app_secret")
+    ///         .attach(router![("/", |res: Res, req: Req| async move {
+    ///             res.send(TextModel::new(&req.user, "Hello")).await?;
+    ///             Ok(())
+    ///         })])
+    ///         .launch()
+    ///         .await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn app_secret(mut self, app_secret: &str) -> Self {
+        self.app_secret = Some(app_secret.to_owned());
+        self.verify_signature = true;
+        self
+    }
+
+    /// `sign_payloads` signs the payloads of the quick replies and of the buttons with `secret`, using HMAC-SHA256.
+    ///
+    /// By default the secret is read from the `PAYLOAD_SECRET` environment variable. The payloads received without a
+    /// valid signature are rejected, so that a client can't jump to another route or forge the `Data` of a payload.
+    /// Changing the secret invalidates the buttons sent before, including the persistent menu.
+    pub fn sign_payloads(mut self, secret: &str) -> Self {
+        self.payload_secret = Some(secret.into());
+        self
+    }
+
+    /// `disable_signature_verification` accepts incoming webhook events without checking their signature.
+    ///
+    /// This is meant for local testing only, never disable the verification in production. Setting an App Secret
+    /// afterwards enables the verification again.
+    pub fn disable_signature_verification(mut self) -> Self {
+        self.app_secret = None;
+        self.verify_signature = false;
+        self
+    }
+
+    /// `static_files` serves the files of the directory `dir` under `mount_path`.
+    ///
+    /// By default the `static` directory is served under `/static`, so that actions can send media hosted next to the
+    /// bot with `req.host`.
+    pub fn static_files(mut self, mount_path: &str, dir: &str) -> Self {
+        self.static_files = Some((mount_path.to_owned(), dir.to_owned()));
+        self
+    }
+
+    /// `disable_static_files` stops serving the `static` directory.
+    pub fn disable_static_files(mut self) -> Self {
+        self.static_files = None;
+        self
+    }
+
+    /// `shutdown_timeout` sets how long a graceful shutdown waits, 30 seconds by default.
+    ///
+    /// On `SIGINT` or `SIGTERM`, `/readyz` answers `503 Service Unavailable` and the server stops accepting webhook
+    /// events, then waits up to this timeout for the open requests. It then waits up to this timeout again for the running actions, their pending sends and the events
+    /// already queued, before giving up on them.
+    pub fn shutdown_timeout(mut self, timeout: Duration) -> Self {
+        self.shutdown_timeout = timeout;
+        self
+    }
+
+    /// `hash_user_ids` records a hash of the PSID of the users in the tracing spans, instead of the PSID itself.
+    ///
+    /// Use it when the spans are shipped to a collector that must not hold personal data. The same PSID always gives
+    /// the same hash, so the events of a user can still be followed. See the `telemetry` module.
+    pub fn hash_user_ids(mut self) -> Self {
+        self.hash_user_ids = true;
         self
     }
 
+    /// `launch` starts the server and the workers, and runs until the server receives `SIGINT` or `SIGTERM`.
+    ///
+    /// The endpoints and the errors are logged with `tracing`. When no subscriber is installed, for instance with
+    /// `telemetry::init`, and the `telemetry` feature is enabled, `launch` installs one printing them to stderr.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no App Secret is set and the signature verification was not disabled, or if the server
+    /// can't bind its address.
     pub async fn launch(self) -> error::Result<()> {
+        if self.verify_signature && self.app_secret.is_none() {
+            return Err(ConfigError::Missing("app_secret".into()).into());
+        }
+        #[cfg(feature = "telemetry")]
+        telemetry::init_fallback();
         run_server(self).await?;
         Ok(())
     }
+
+    /// `start_workers` applies the global middlewares, then starts the workers and connects the webhook to them.
+    ///
+    /// `launch` calls this method itself. It is needed to mount the webhook in an existing actix-web server with
+    /// [`configure`](App::configure) or [`scope`](App::scope): the webhook answers `503 Service Unavailable` until the
+    /// workers are started. The returned `WorkerPool` must be shut down once the server has stopped.
+    ///
+    /// Unlike `launch`, this method does not install a `tracing` subscriber: install one, for instance with
+    /// `telemetry::init`, to see the logs and the errors of the actions.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use actix_web::{App as ActixApp, HttpServer};
+    /// use russenger::prelude::*;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<()> {
+    ///     let (app, workers) = App::init().await?
+    ///         .attach(router![("/", |res: Res, req: Req| async move {
+    ///             res.send(TextModel::new(&req.user, "Hello")).await?;
+    ///             Ok(())
+    ///         })])
+    ///         .start_workers();
+    ///
+    ///     HttpServer::new(move || ActixApp::new().service(app.scope("/messenger")))
+    ///         .bind(("0.0.0.0", 8080))?
+    ///         .run()
+    ///         .await?;
+    ///
+    ///     workers.shutdown().await;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn start_workers(mut self) -> (Self, WorkerPool) {
+        Arc::get_mut(&mut self.router)
+            .expect("Router already shared")
+            .apply_middlewares();
+        let workers = WorkerPool::start(&self, self.workers);
+        self.dispatcher = Some(workers.dispatcher());
+        (self, workers)
+    }
+
+    /// `configure` registers the webhook, the health endpoints and the static files if enabled, in an actix-web `ServiceConfig`.
+    ///
+    /// The workers must be started with [`start_workers`](App::start_workers) first.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use actix_web::{App as ActixApp, HttpServer};
+    /// use russenger::prelude::*;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<()> {
+    ///     let (app, workers) = App::init().await?.disable_static_files().start_workers();
+    ///
+    ///     HttpServer::new(move || ActixApp::new().configure(|cfg| app.configure(cfg)))
+    ///         .bind(("0.0.0.0", 8080))?
+    ///         .run()
+    ///         .await?;
+    ///
+    ///     workers.shutdown().await;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn configure(&self, cfg: &mut web::ServiceConfig) {
+        cfg.app_data(web::Data::new(self.clone()))
+            .service(services::handlers::webhook_verify)
+            .service(services::handlers::webhook_core)
+            .service(services::health::healthz)
+            .service(services::health::readyz);
+        #[cfg(feature = "metrics")]
+        cfg.service(metrics::metrics);
+        if let Some((mount_path, dir)) = &self.static_files {
+            cfg.service(fs::Files::new(mount_path, dir).show_files_listing());
+        }
+    }
+
+    /// `scope` returns an actix-web `Scope` serving the webhook under `path`, like `/messenger/webhook`.
+    ///
+    /// The workers must be started with [`start_workers`](App::start_workers) first. See [`configure`](App::configure).
+    pub fn scope(&self, path: &str) -> Scope {
+        web::scope(path).configure(|cfg| self.configure(cfg))
+    }
 }
 
-fn print_info(host: &str, port: u16) {
+fn print_info(host: &str, port: u16, verify_signature: bool) {
     let url = format!("http://{}:{}", host, port);
-    println!("Endpoints:");
-    println!("  GET: {}/webhook - Webhook verification endpoint", url);
-    println!("  POST: {}/webhook - Webhook core endpoint", url);
+    tracing::info!("GET: {url}/webhook - Webhook verification endpoint");
+    tracing::info!("POST: {url}/webhook - Webhook core endpoint");
+    tracing::info!("GET: {url}/healthz - Liveness endpoint");
+    tracing::info!("GET: {url}/readyz - Readiness endpoint");
+    #[cfg(feature = "metrics")]
+    tracing::info!("GET: {url}/metrics - Prometheus metrics endpoint");
+    if !verify_signature {
+        tracing::warn!(
+            "The signature verification is disabled, webhook events are not authenticated"
+        );
+    }
 }
 
 async fn run_server(app: App) -> error::Result<()> {
+    let (app, workers) = app.start_workers();
+
     let addr = app.addr.clone();
-    print_info(&addr.0, addr.1);
-    HttpServer::new(move || {
-        ActixApp::new()
-            .app_data(web::Data::new(app.clone()))
-            .service(services::handlers::webhook_verify)
-            .service(services::handlers::webhook_core)
-            .service(fs::Files::new("/static", "static").show_files_listing())
-    })
-    .bind(addr)
-    .unwrap()
-    .run()
-    .await
-    .unwrap();
-    Ok(())
+    print_info(&addr.0, addr.1, app.verify_signature);
+    let shutdown_timeout = app.shutdown_timeout;
+    let shutting_down = app.shutting_down.clone();
+    let server = HttpServer::new(move || ActixApp::new().configure(|cfg| app.configure(cfg)))
+        .shutdown_timeout(shutdown_timeout.as_secs())
+        .disable_signals();
+    let result = match server.bind(addr) {
+        Ok(server) => {
+            let server = server.run();
+            let handle = server.handle();
+            tokio::spawn(async move {
+                shutdown_signal().await;
+                tracing::info!("Shutting down");
+                // Fail the readiness probes right away, while the open requests finish.
+                shutting_down.store(true, Ordering::Relaxed);
+                handle.stop(true).await;
+            });
+            server.await
+        }
+        Err(err) => Err(err),
+    };
+
+    if tokio::time::timeout(shutdown_timeout, workers.shutdown())
+        .await
+        .is_err()
+    {
+        tracing::warn!(
+            ?shutdown_timeout,
+            "Workers did not finish in time, dropping the running actions"
+        );
+    }
+    Ok(result?)
+}
+
+/// Waits for `SIGINT`, or for `SIGTERM` on Unix.
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = terminate.recv() => {}
+                }
+                return;
+            }
+            Err(err) => tracing::warn!(error = %err, "Error listening for SIGTERM"),
+        }
+    }
+    if let Err(err) = tokio::signal::ctrl_c().await {
+        tracing::warn!(error = %err, "Error listening for SIGINT");
+        std::future::pending::<()>().await;
+    }
 }
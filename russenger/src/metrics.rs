@@ -0,0 +1,219 @@
+//! The `metrics` module records Prometheus metrics about the traffic of the bot, behind the `metrics` feature.
+//!
+//! With the feature enabled, the application serves the metrics in the Prometheus text format at `GET /metrics`:
+//!
+//! * `russenger_events_received_total{type}`: The webhook events received, by type (`text`, `quick_reply`, `attachment`, `postback`).
+//! * `russenger_action_duration_seconds{route}`: The time spent in the actions, by route.
+//! * `russenger_action_errors_total{route}`: The actions that returned an error, by route.
+//! * `russenger_graph_request_duration_seconds{endpoint, status}`: The time spent sending to the Graph API, retries
+//!   included, by endpoint and outcome.
+//! * `russenger_queue_waiting_events_total`: The events that waited for a running action of the same user.
+//! * `russenger_queue_dropped_events_total`: The events dropped because the queue of their user was full.
+//! * `russenger_db_query_duration_seconds{operation}`: The time spent in the queries of `Query`, by operation.
+//! * `russenger_db_query_errors_total{operation}`: The queries of `Query` that failed, by operation.
+//!
+//! The metrics are shared by every `App` of the process. Without the feature, nothing is recorded.
+//!
+//! # Examples
+//!
+//! ```toml
+//! [dependencies]
+//! russenger = { version = "0.3.0", features = ["postgres", "metrics"] }
+//! ```
+//!
+//! ```yaml
+//! scrape_configs:
+//!   - job_name: russenger
+//!     static_configs:
+//!       - targets: ["localhost:2453"]
+//! ```
+#[cfg(feature = "metrics")]
+pub use prometheus_metrics::metrics;
+#[cfg(feature = "metrics")]
+pub(crate) use prometheus_metrics::{
+    action_finished, db_query, event_dropped, event_received, event_waiting, graph_request,
+};
+
+#[cfg(not(feature = "metrics"))]
+pub(crate) use noop::*;
+
+#[cfg(feature = "metrics")]
+mod prometheus_metrics {
+    use std::{sync::LazyLock, time::Duration};
+
+    use actix_web::{get, HttpResponse};
+    use prometheus::{
+        HistogramOpts, HistogramVec, IntCounter, IntCounterVec, Opts, Registry, TextEncoder,
+    };
+
+    use crate::core::graph::GraphError;
+    use crate::services::Messaging;
+
+    struct Metrics {
+        registry: Registry,
+        events_received: IntCounterVec,
+        action_duration: HistogramVec,
+        action_errors: IntCounterVec,
+        graph_request_duration: HistogramVec,
+        queue_waiting_events: IntCounter,
+        queue_dropped_events: IntCounter,
+        db_query_duration: HistogramVec,
+        db_query_errors: IntCounterVec,
+    }
+
+    static METRICS: LazyLock<Metrics> = LazyLock::new(|| {
+        let registry = Registry::new();
+        let counter_vec = |name: &str, help: &str, labels: &[&str]| {
+            let counter = IntCounterVec::new(Opts::new(name, help), labels).unwrap();
+            registry.register(Box::new(counter.clone())).unwrap();
+            counter
+        };
+        let counter = |name: &str, help: &str| {
+            let counter = IntCounter::new(name, help).unwrap();
+            registry.register(Box::new(counter.clone())).unwrap();
+            counter
+        };
+        let histogram_vec = |name: &str, help: &str, labels: &[&str]| {
+            let histogram = HistogramVec::new(HistogramOpts::new(name, help), labels).unwrap();
+            registry.register(Box::new(histogram.clone())).unwrap();
+            histogram
+        };
+
+        Metrics {
+            events_received: counter_vec(
+                "russenger_events_received_total",
+                "Webhook events received, by type",
+                &["type"],
+            ),
+            action_duration: histogram_vec(
+                "russenger_action_duration_seconds",
+                "Time spent in the actions, by route",
+                &["route"],
+            ),
+            action_errors: counter_vec(
+                "russenger_action_errors_total",
+                "Actions that returned an error, by route",
+                &["route"],
+            ),
+            graph_request_duration: histogram_vec(
+                "russenger_graph_request_duration_seconds",
+                "Time spent sending to the Graph API, by endpoint and outcome",
+                &["endpoint", "status"],
+            ),
+            queue_waiting_events: counter(
+                "russenger_queue_waiting_events_total",
+                "Events that waited for a running action of the same user",
+            ),
+            queue_dropped_events: counter(
+                "russenger_queue_dropped_events_total",
+                "Events dropped because the queue of their user was full",
+            ),
+            db_query_duration: histogram_vec(
+                "russenger_db_query_duration_seconds",
+                "Time spent in the database queries, by operation",
+                &["operation"],
+            ),
+            db_query_errors: counter_vec(
+                "russenger_db_query_errors_total",
+                "Database queries that failed, by operation",
+                &["operation"],
+            ),
+            registry,
+        }
+    });
+
+    /// The outcome of a Graph API request, used as the `status` label.
+    fn graph_status(result: Result<(), &GraphError>) -> &'static str {
+        match result {
+            Ok(()) => "success",
+            Err(GraphError::RateLimited(_)) => "rate_limited",
+            Err(GraphError::RecipientUnavailable(_)) => "recipient_unavailable",
+            Err(GraphError::OutsideWindow(_)) => "outside_window",
+            Err(GraphError::InvalidToken(_)) => "invalid_token",
+            Err(GraphError::Api { .. }) => "api_error",
+            Err(GraphError::InvalidResponse { .. }) => "invalid_response",
+            Err(GraphError::Transport(_)) => "transport_error",
+            Err(GraphError::Serialize(_)) => "serialize_error",
+        }
+    }
+
+    pub(crate) fn event_received(messaging: &Messaging) {
+        METRICS
+            .events_received
+            .with_label_values(&[messaging.kind()])
+            .inc();
+    }
+
+    pub(crate) fn action_finished(route: &str, elapsed: Duration, success: bool) {
+        METRICS
+            .action_duration
+            .with_label_values(&[route])
+            .observe(elapsed.as_secs_f64());
+        if !success {
+            METRICS.action_errors.with_label_values(&[route]).inc();
+        }
+    }
+
+    pub(crate) fn graph_request(
+        endpoint: &str,
+        elapsed: Duration,
+        result: Result<(), &GraphError>,
+    ) {
+        let status = graph_status(result);
+        METRICS
+            .graph_request_duration
+            .with_label_values(&[endpoint, status])
+            .observe(elapsed.as_secs_f64());
+    }
+
+    pub(crate) fn event_waiting() {
+        METRICS.queue_waiting_events.inc();
+    }
+
+    pub(crate) fn event_dropped() {
+        METRICS.queue_dropped_events.inc();
+    }
+
+    pub(crate) fn db_query(operation: &str, elapsed: Duration, success: bool) {
+        METRICS
+            .db_query_duration
+            .with_label_values(&[operation])
+            .observe(elapsed.as_secs_f64());
+        if !success {
+            METRICS
+                .db_query_errors
+                .with_label_values(&[operation])
+                .inc();
+        }
+    }
+
+    #[get("/metrics")]
+    pub async fn metrics() -> HttpResponse {
+        match TextEncoder::new().encode_to_string(&METRICS.registry.gather()) {
+            Ok(body) => HttpResponse::Ok()
+                .content_type("text/plain; version=0.0.4")
+                .body(body),
+            Err(err) => HttpResponse::InternalServerError().body(err.to_string()),
+        }
+    }
+}
+
+#[cfg(not(feature = "metrics"))]
+mod noop {
+    use std::time::Duration;
+
+    use crate::core::graph::GraphError;
+    use crate::services::Messaging;
+
+    pub(crate) fn event_received(_: &Messaging) {}
+
+    pub(crate) fn action_finished(_: &str, _: Duration, _: bool) {}
+
+    pub(crate) fn graph_request(_: &str, _: Duration, _: Result<(), &GraphError>) {}
+
+    pub(crate) fn event_waiting() {}
+
+    pub(crate) fn event_dropped() {}
+
+    pub(crate) fn db_query(_: &str, _: Duration, _: bool) {}
+}
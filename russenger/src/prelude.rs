@@ -2,9 +2,12 @@
 //!
 //! # Re-exports
 //!
+//! * `App`, `AppConfig`: The application and the settings used to create it.
 //! * `Req`: A struct that represents a request from a user.
-//! * `Res`, `SendResult`: A struct and a type alias that represent a response that can be sent to a user.
-//! * `Button`, `Data`, `GenericElement`, `GenericModel`, `GetStartedModel`, `MediaModel`, `Payload`, `PersistentMenuModel`, `QuickReply`, `QuickReplyModel`, `SenderActionModel`, `TextModel`, `ResponseModel`: Various response models that can be sent to a user.
+//! * `Session`, `SessionValue`: The values stored for a user between their actions, and the types stored under their own key.
+//! * `action`, `FromRequest`, `Attachments`, `Json`, `Text`, `Page`: The attribute macro defining an action with extracted arguments, and the extractors.
+//! * `Res`, `SendResponse`, `GraphError`: A struct that represents a response that can be sent to a user, and the result of sending it.
+//! * `BackModel`, `Button`, `Data`, `GenericElement`, `GenericModel`, `GetStartedModel`, `MediaModel`, `NextModel`, `Payload`, `RussengerPayload`, `PersistentMenuModel`, `QuickReply`, `QuickReplyModel`, `SenderActionModel`, `TextModel`, `ResponseModel`: Various response models that can be sent to a user.
 //!
 //! # Examples
 //!
@@ -30,22 +33,33 @@
 //!     Ok(())
 //! }
 //! ```
-pub use crate::core::{request::Req, response::Res, router::Router};
+pub use crate::core::{
+    extract::{Attachments, FromRequest, Json, Text},
+    graph::{GraphError, RetryPolicy, SendResponse},
+    middleware::{Logger, Middleware, Next, TypingIndicator},
+    request::Req,
+    response::Res,
+    router::{Params, Router},
+};
+pub use crate::db::{Session, SessionValue};
 pub use crate::error::{self, Result};
 pub use crate::response_models::{
     button::{Button, ButtonModel},
-    data::Data,
+    data::{Data, Page},
     generic::{GenericElement, GenericModel},
     get_started::GetStartedButtonModel,
     media::MediaModel,
-    next::NextModel,
-    payload::Payload,
+    next::{BackModel, NextModel},
+    payload::{Payload, PayloadError, RussengerPayload},
     persistent_menu::PersistentMenuModel,
     quick_replies::{QuickReply, QuickReplyModel},
     sender_action::{Actions::*, SenderActionModel},
     text::TextModel,
     ResponseModel,
 };
-pub use crate::router;
 pub use crate::rusql_alchemy::{self, prelude::*};
-pub use crate::App;
+pub use crate::services::{
+    queue::OverflowPolicy, Attachment, AttachmentPayload, AttachmentType, Coordinates,
+};
+pub use crate::{action, router};
+pub use crate::{App, AppConfig};
@@ -25,7 +25,6 @@
 //!
 //! # Constants
 //!
-//! * `MAX_VALUE_AUTHORIZED`: The maximum value that is authorized.
 //! * `MIN_PAGE`: The minimum page number.
 //! * `MAX_PAGE`: The maximum page number.
 //!
@@ -69,7 +68,6 @@ pub mod data {
     use crate::error::Result;
     use serde::{Deserialize, Serialize};
 
-    const MAX_VALUE_AUTHORIZED: usize = 500;
     const MIN_PAGE: usize = 0;
     pub const MAX_PAGE: usize = 10;
 
@@ -89,20 +87,6 @@ pub mod data {
         }
     }
 
-    trait Verify {
-        fn verify(&self) -> String;
-    }
-
-    impl Verify for String {
-        fn verify(&self) -> String {
-            if self.len() >= MAX_VALUE_AUTHORIZED {
-                self[..MAX_VALUE_AUTHORIZED].to_string()
-            } else {
-                self.clone()
-            }
-        }
-    }
-
     /// The `Data` struct represents a data object with a value and an optional page.
     ///
     /// This struct is used to store and manipulate data. It contains a `value` field, which is a serialized JSON string, and a `page` field, which is an optional `Page` struct.
@@ -112,6 +96,9 @@ pub mod data {
     /// * `value`: The value of the data. This is a serialized JSON string.
     /// * `page`: The page of the data. This is an optional `Page` struct.
     ///
+    /// The value is not limited in size: when a payload holding a large `Data` is sent, the `Data` is stored in the
+    /// database and the payload only carries its key.
+    ///
     /// # Methods
     ///
     /// * `new`: This method creates a new `Data`. It takes a value and an optional page as arguments, serializes the value into a JSON string, and returns a `Data` with the serialized string and the page.
@@ -150,12 +137,12 @@ pub mod data {
         /// * `Data`: The created `Data`.
         ///
         pub fn new(value: impl Serialize) -> Self {
-            let value = serde_json::to_string(&value).unwrap_or_default().verify();
+            let value = serde_json::to_string(&value).unwrap_or_default();
             Self { value, page: None }
         }
 
         pub fn new_with_page(value: impl Serialize, page: Option<Page>) -> Self {
-            let value = serde_json::to_string(&value).unwrap_or_default().verify();
+            let value = serde_json::to_string(&value).unwrap_or_default();
             Self { value, page }
         }
 
@@ -4,6 +4,12 @@ use super::{
     quick_replies::{QuickReply, QuickReplyModel},
 };
 
+/// The path of the payload of the "Back" quick reply.
+///
+/// It is handled before the router: the current step of the user is removed from their history, and the action of
+/// their previous step runs again with the same `Data`.
+pub const BACK_PATH: &str = "/russenger/back";
+
 #[allow(non_snake_case)]
 pub fn NextModel<'n>(sender: &'n str, data: Data, path: &'n str) -> QuickReplyModel<'n> {
     QuickReplyModel::new(
@@ -16,3 +22,22 @@ pub fn NextModel<'n>(sender: &'n str, data: Data, path: &'n str) -> QuickReplyMo
         )],
     )
 }
+
+/// Returns a "Back" quick reply, which sends the user back to their previous step.
+///
+/// # Examples
+///
+/// ```rust
+/// use russenger::prelude::*;
+///
+/// async fn details(res: Res, req: Req) -> Result<()> {
+///     res.send(TextModel::new(&req.user, "Here are the details")).await?;
+///     res.send(BackModel(&req.user)).await?;
+///
+///     Ok(())
+/// }
+/// ```
+#[allow(non_snake_case)]
+pub fn BackModel(sender: &str) -> QuickReplyModel<'_> {
+    QuickReplyModel::new(sender, "Navigation", vec![QuickReply::back()])
+}
@@ -9,6 +9,12 @@
 //! * `path: String` - The path of the action to be performed.
 //! * `data: Option<Data>` - The data associated with the action. This field is optional.
 //!
+//! With `App::sign_payloads`, every payload sent by `Res::send` is signed with HMAC-SHA256, and the payloads received
+//! from the users are rejected unless their signature is valid. A payload can also expire with `expires_in`.
+//!
+//! When the serialized payload is longer than the `MAX_PAYLOAD_LENGTH` accepted by Facebook, `Res::send` stores its
+//! data in the database and sends a payload carrying a short key instead. The data is loaded back transparently.
+//!
 //! ### Methods
 //!
 //! * `new<A: Action>(action: A, data: Option<Data>) -> Self` - Creates a new `Payload` instance. The `action` parameter is the action to be performed, and the `data` parameter is the data associated with the action.
@@ -51,9 +57,15 @@
 //! * `FromStr`
 //! * `ToString`
 //! * `Default`
-use std::str::FromStr;
+use std::{
+    fmt,
+    str::FromStr,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
-use serde::{Deserialize, Serialize};
+use hmac::{Hmac, Mac};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sha2::Sha256;
 
 use super::data::Data;
 /// `Payload` is a struct that represents the payload of a request in a Messenger conversation.
@@ -98,6 +110,12 @@ use super::data::Data;
 pub struct Payload {
     action_path: String,
     data: Option<Data>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    key: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    expires_at: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    signature: Option<String>,
 }
 
 impl Payload {
@@ -130,6 +148,9 @@ impl Payload {
         Self {
             action_path: action_path.to_owned(),
             data,
+            key: None,
+            expires_at: None,
+            signature: None,
         }
     }
 
@@ -150,6 +171,114 @@ impl Payload {
     pub fn get_data(&self) -> Data {
         self.data.clone().unwrap_or_default()
     }
+
+    /// Makes the payload expire after `ttl`.
+    ///
+    /// An expired payload is rejected when the user taps its button, see `App::on_rejected_payload`. Without
+    /// `App::sign_payloads`, the expiry can be changed by the client.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::time::Duration;
+    /// use russenger::prelude::*;
+    ///
+    /// async fn offer(res: Res, req: Req) -> Result<()> {
+    ///     let payload = Payload::new("/buy", Some(Data::new("discount"))).expires_in(Duration::from_secs(3600));
+    ///     let quick_replies = vec![QuickReply::new("Buy now", None, payload)];
+    ///     res.send(QuickReplyModel::new(&req.user, "This offer ends in one hour", quick_replies)).await?;
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn expires_in(mut self, ttl: Duration) -> Self {
+        self.expires_at = Some(now().saturating_add(ttl.as_secs()));
+        self
+    }
+
+    /// Returns a payload with the same path, whose data is stored in the database under `key`.
+    pub(crate) fn stored(&self, key: String) -> Self {
+        Self {
+            data: None,
+            key: Some(key),
+            signature: None,
+            ..self.clone()
+        }
+    }
+
+    /// Returns the key of the data stored in the database, if the payload was too long to be sent.
+    pub(crate) fn get_key(&self) -> Option<&str> {
+        self.key.as_deref()
+    }
+
+    /// Returns the payload signed with `secret`.
+    pub(crate) fn sign(self, secret: &str) -> Self {
+        let signature = hex::encode(self.mac(secret).finalize().into_bytes());
+        Self {
+            signature: Some(signature),
+            ..self
+        }
+    }
+
+    /// Checks that the payload is signed with `secret`, if it is set, and that it has not expired.
+    pub(crate) fn verify(&self, secret: Option<&str>) -> Result<(), PayloadError> {
+        if let Some(secret) = secret {
+            let signature = self
+                .signature
+                .as_deref()
+                .ok_or(PayloadError::MissingSignature)?;
+            let signature = hex::decode(signature).map_err(|_| PayloadError::InvalidSignature)?;
+            self.mac(secret)
+                .verify_slice(&signature)
+                .map_err(|_| PayloadError::InvalidSignature)?;
+        }
+        match self.expires_at {
+            Some(expires_at) if expires_at <= now() => Err(PayloadError::Expired),
+            _ => Ok(()),
+        }
+    }
+
+    /// The HMAC-SHA256 of the payload without its signature.
+    fn mac(&self, secret: &str) -> Hmac<Sha256> {
+        let unsigned = Self {
+            signature: None,
+            ..self.clone()
+        };
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key size");
+        mac.update(unsigned.to_string().as_bytes());
+        mac
+    }
+}
+
+/// The reason a payload received from a user was rejected.
+#[derive(Debug)]
+pub enum PayloadError {
+    /// The payload is not signed, while `App::sign_payloads` is set.
+    MissingSignature,
+    /// The signature does not match the payload, which was modified or signed with another secret.
+    InvalidSignature,
+    /// The payload expired, or the `Data` stored for it expired or was deleted.
+    Expired,
+}
+
+impl fmt::Display for PayloadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingSignature => write!(f, "The payload is not signed"),
+            Self::InvalidSignature => write!(f, "The signature of the payload is invalid"),
+            Self::Expired => write!(f, "The payload expired"),
+        }
+    }
+}
+
+impl std::error::Error for PayloadError {}
+
+/// The current time, in seconds since the Unix epoch.
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
 }
 
 impl FromStr for Payload {
@@ -174,6 +303,179 @@ impl Default for Payload {
         Payload {
             action_path: "/".to_owned(),
             data: None,
+            key: None,
+            expires_at: None,
+            signature: None,
         }
     }
 }
+
+pub use russenger_macros::RussengerPayload;
+
+/// `RussengerPayload` is implemented by the enums whose variants are sent as payloads, with
+/// `#[derive(RussengerPayload)]`.
+///
+/// Every variant is routed to its own path, and the whole variant is sent as the `Data` of the payload. The enum is
+/// registered with `Router::payload`, whose action receives the decoded variant.
+///
+/// # Examples
+///
+/// ```rust
+/// use russenger::prelude::*;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(RussengerPayload, Serialize, Deserialize)]
+/// #[payload(prefix = "/shop")]
+/// enum Shop {
+///     Catalog,
+///     Product { id: u32 },
+/// }
+///
+/// async fn index(res: Res, req: Req) -> Result<()> {
+///     let quick_replies = vec![
+///         QuickReply::new("Catalog", None, Shop::Catalog.into()),
+///         QuickReply::new("Best seller", None, Shop::Product { id: 42 }.into()),
+///     ];
+///     res.send(QuickReplyModel::new(&req.user, "Welcome to the shop", quick_replies)).await?;
+///     Ok(())
+/// }
+///
+/// async fn shop(res: Res, req: Req, shop: Shop) -> Result<()> {
+///     let message = match shop {
+///         Shop::Catalog => "Here is the catalog".to_owned(),
+///         Shop::Product { id } => format!("Here is the product {id}"),
+///     };
+///     res.send(TextModel::new(&req.user, message)).await?;
+///     Ok(())
+/// }
+///
+/// assert_eq!(Shop::PATHS, ["/shop/catalog", "/shop/product"]);
+/// let router = Router::new().add("/", index).payload(shop);
+/// ```
+///
+/// Two variants can't have the same path:
+///
+/// ```rust,compile_fail
+/// use russenger::prelude::*;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(RussengerPayload, Serialize, Deserialize)]
+/// enum Menu {
+///     Help,
+///     #[payload(path = "/help")]
+///     Support,
+/// }
+/// ```
+pub trait RussengerPayload: Serialize + DeserializeOwned + Send + 'static {
+    /// The paths of the variants.
+    const PATHS: &'static [&'static str];
+
+    /// Returns the path of the variant.
+    fn path(&self) -> &'static str;
+
+    /// Returns the payload of the variant, carrying the variant as its `Data`.
+    fn to_payload(&self) -> Payload {
+        Payload::new(self.path(), Some(Data::new(self)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &str = "payload_secret";
+
+    fn signed() -> Payload {
+        Payload::new("/buy", Some(Data::new("discount"))).sign(SECRET)
+    }
+
+    /// Edits the payload as a client would, through its JSON.
+    fn tamper(payload: &Payload, from: &str, to: &str) -> Payload {
+        let tampered = payload.to_string().replace(from, to);
+        assert_ne!(tampered, payload.to_string(), "{from} not found");
+        Payload::from_str(&tampered).unwrap()
+    }
+
+    #[test]
+    fn accepts_a_valid_signature() {
+        assert!(signed().verify(Some(SECRET)).is_ok());
+
+        let received = Payload::from_str(&signed().to_string()).unwrap();
+        assert!(received.verify(Some(SECRET)).is_ok());
+    }
+
+    #[test]
+    fn rejects_a_tampered_path() {
+        let payload = tamper(&signed(), "/buy", "/admin");
+        assert!(matches!(
+            payload.verify(Some(SECRET)),
+            Err(PayloadError::InvalidSignature)
+        ));
+    }
+
+    #[test]
+    fn rejects_tampered_data() {
+        let payload = tamper(&signed(), "discount", "free");
+        assert!(matches!(
+            payload.verify(Some(SECRET)),
+            Err(PayloadError::InvalidSignature)
+        ));
+    }
+
+    #[test]
+    fn rejects_a_tampered_expiry() {
+        let payload = Payload::new("/buy", None)
+            .expires_in(Duration::from_secs(60))
+            .sign(SECRET);
+        let expires_at = payload.expires_at.unwrap().to_string();
+        let payload = tamper(&payload, &expires_at, "99999999999");
+        assert!(matches!(
+            payload.verify(Some(SECRET)),
+            Err(PayloadError::InvalidSignature)
+        ));
+    }
+
+    #[test]
+    fn rejects_a_signature_made_with_another_secret() {
+        let payload = Payload::new("/buy", None).sign("another_secret");
+        assert!(matches!(
+            payload.verify(Some(SECRET)),
+            Err(PayloadError::InvalidSignature)
+        ));
+
+        let payload = Payload {
+            signature: Some("not hex".to_owned()),
+            ..signed()
+        };
+        assert!(matches!(
+            payload.verify(Some(SECRET)),
+            Err(PayloadError::InvalidSignature)
+        ));
+    }
+
+    #[test]
+    fn rejects_an_unsigned_payload_when_a_secret_is_set() {
+        let payload = Payload::new("/buy", None);
+        assert!(matches!(
+            payload.verify(Some(SECRET)),
+            Err(PayloadError::MissingSignature)
+        ));
+        assert!(payload.verify(None).is_ok());
+    }
+
+    #[test]
+    fn rejects_an_expired_payload() {
+        let expired = Payload {
+            expires_at: Some(now() - 1),
+            ..Payload::new("/buy", None)
+        };
+        assert!(matches!(expired.verify(None), Err(PayloadError::Expired)));
+        assert!(matches!(
+            expired.sign(SECRET).verify(Some(SECRET)),
+            Err(PayloadError::Expired)
+        ));
+
+        let payload = Payload::new("/buy", None).expires_in(Duration::from_secs(60));
+        assert!(payload.sign(SECRET).verify(Some(SECRET)).is_ok());
+    }
+}
@@ -73,6 +73,7 @@
 //! [Facebook Messenger Platform - Quick Replies](https://developers.facebook.com/docs/messenger-platform/send-messages/quick-replies)
 use serde::Serialize;
 
+use super::next::BACK_PATH;
 use super::ResponseModel;
 use super::{payload::Payload, recipient::Recipient};
 
@@ -151,6 +152,30 @@ impl QuickReply {
             image_url: image_url.cloned(),
         }
     }
+
+    /// Creates a "Back" quick reply, which runs the previous step of the user again.
+    ///
+    /// See [`BackModel`](crate::response_models::next::BackModel).
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use russenger::prelude::*;
+    ///
+    /// async fn choose_size(res: Res, req: Req) -> Result<()> {
+    ///     let quick_replies = vec![
+    ///         QuickReply::new("Small", None, Payload::new("/size", Some(Data::new("S")))),
+    ///         QuickReply::new("Large", None, Payload::new("/size", Some(Data::new("L")))),
+    ///         QuickReply::back(),
+    ///     ];
+    ///     res.send(QuickReplyModel::new(&req.user, "Choose a size", quick_replies)).await?;
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn back() -> Self {
+        Self::new("Back", None, Payload::new(BACK_PATH, None))
+    }
 }
 
 #[derive(Serialize, Debug)]
@@ -1,100 +1,315 @@
-use std::{io, str::FromStr, sync::Arc};
+use std::{io, str::FromStr, sync::Arc, time::Instant};
 
-use actix_web::{dev, get, post, web, HttpResponse};
+use actix_web::{dev, get, post, web, HttpRequest, HttpResponse};
+use sha2::{Digest, Sha256};
+use tracing::{error, info_span, warn, Instrument};
 
 use crate::{
-    core::{request::Req, response::Res, router::Router},
+    core::{graph::GraphClient, request::Req, response::Res, router::Router},
     db::Query,
     error::Result,
-    response_models::{data::Data, payload::Payload},
+    metrics,
+    response_models::{
+        data::Data,
+        next::BACK_PATH,
+        payload::{Payload, PayloadError},
+    },
     App,
 };
 
-use super::{InComingData, WebQuery};
+use super::{
+    queue::{Event, UserKey},
+    signature::{verify_signature, SIGNATURE_HEADER},
+    worker::Job,
+    Attachment, InComingData, Messaging, WebQuery,
+};
 
 enum Message<'a> {
-    Payload(&'a str, &'a str, &'a str, Arc<Query>, String, String),
-    TextMessage(&'a str, &'a str, &'a str, Arc<Query>, String, String),
+    Payload(&'a str),
+    TextMessage(&'a str, Vec<Attachment>),
+}
+
+/// The user an event was received from, with the database and the Graph API client of their page.
+struct Context<'a> {
+    user: &'a UserKey,
+    host: &'a str,
+    query: Arc<Query>,
+    graph: GraphClient,
+    payload_secret: Option<Arc<str>>,
 }
 
-async fn handle(message: Message<'_>, router: Arc<Router>) -> Result<()> {
+impl Context<'_> {
+    fn res(&self) -> Res {
+        Res::new(&self.user.user, self.query.clone(), self.graph.clone())
+            .sign_payloads(self.payload_secret.clone())
+    }
+
+    fn req(&self, data: Data) -> Req {
+        Req {
+            page_id: self.user.page_id.clone(),
+            ..Req::new(&self.user.user, self.query.clone(), data, self.host)
+        }
+    }
+}
+
+async fn handle(message: Message<'_>, context: &Context<'_>, router: &Router) -> Result<()> {
     match message {
-        Message::Payload(user, payload, host, query, version, token) => {
+        Message::Payload(payload) => {
             let payload = Payload::from_str(payload).unwrap_or_default();
-            let data = payload.get_data();
-            let res = Res::new(user, query.clone(), version, token);
-            let req = Req::new(user, query, data, host);
-            let path = payload.get_path();
-            let action = router.routes.get(&path).ok_or_else(|| {
-                io::Error::new(
-                    io::ErrorKind::NotFound,
-                    format!("Action not found  for path {path}"),
-                )
-            })?;
-            action(res, req).await?
+            if let Err(err) = payload.verify(context.payload_secret.as_deref()) {
+                let req = context.req(Data::default());
+                return reject(router, &payload.get_path(), context.res(), req, err).await;
+            }
+            let (path, data) = if payload.get_path() == BACK_PATH {
+                match context.query.back(&context.user.user).await? {
+                    Some(step) => (step.path, step.data),
+                    None => ("/".to_owned(), Data::default()),
+                }
+            } else if let Some(key) = payload.get_key() {
+                match context.query.load_payload(key).await? {
+                    Some(data) => (payload.get_path(), data),
+                    None => {
+                        let req = context.req(Data::default());
+                        let (path, err) = (payload.get_path(), PayloadError::Expired);
+                        return reject(router, &path, context.res(), req, err).await;
+                    }
+                }
+            } else {
+                (payload.get_path(), payload.get_data())
+            };
+            dispatch(router, &path, context.res(), context.req(data)).await
+        }
+        Message::TextMessage(text_message, attachments) => {
+            let path = context
+                .query
+                .get_path(&context.user.user)
+                .await?
+                .unwrap_or("/".to_string());
+            let req = Req {
+                attachments,
+                ..context.req(Data::new(text_message))
+            };
+            dispatch(router, &path, context.res(), req).await
+        }
+    }
+}
+
+async fn dispatch(router: &Router, path: &str, res: Res, req: Req) -> Result<()> {
+    let req = Req {
+        path: path.to_owned(),
+        ..req
+    };
+    let start = Instant::now();
+    let (route, result) = match router.find(path) {
+        Some((route, action, params)) => {
+            if let Err(err) = req.query.push_history(&req.user, path, &req.data).await {
+                warn!(error = %err, "Error recording the history of the user");
+            }
+            let req = Req {
+                params,
+                ..req.clone()
+            };
+            let span = info_span!("action", route, path);
+            (route, action(res.clone(), req).instrument(span).await)
         }
-        Message::TextMessage(user, text_message, host, query, version, token) => {
-            let path = query.get_path(user).await?.unwrap_or("/".to_string());
-            let res = Res::new(user, query.clone(), version, token);
-            let req = Req::new(user, query, Data::new(text_message), host);
-            let action = router.routes.get(&path).ok_or_else(|| {
-                io::Error::new(
-                    io::ErrorKind::NotFound,
-                    format!("Action not found  for path {path}"),
-                )
-            })?;
-            action(res, req).await?
+        None => {
+            let span = info_span!("action", route = "not_found", path);
+            let result = not_found(router, path, res.clone(), req.clone())
+                .instrument(span)
+                .await;
+            ("not_found", result)
         }
+    };
+    metrics::action_finished(route, start.elapsed(), result.is_ok());
+
+    match (result, &router.error_handler) {
+        (Err(err), Some(error_handler)) => error_handler(res, req, path.to_owned(), err).await,
+        (result, _) => result,
     }
+}
 
-    Ok(())
+async fn reject(router: &Router, path: &str, res: Res, req: Req, err: PayloadError) -> Result<()> {
+    match &router.rejection_handler {
+        Some(rejection_handler) => {
+            let req = Req {
+                path: path.to_owned(),
+                ..req
+            };
+            rejection_handler(res, req, path.to_owned(), err.into()).await
+        }
+        None => {
+            warn!(path, error = %err, "Rejected a payload");
+            Ok(())
+        }
+    }
+}
+
+async fn not_found(router: &Router, path: &str, res: Res, req: Req) -> Result<()> {
+    if router.reset_unknown_path {
+        res.redirect("/").await?;
+    }
+    match &router.fallback {
+        Some(fallback) => fallback(res, req).await,
+        None => Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("Action not found  for path {path}"),
+        )
+        .into()),
+    }
 }
 
 #[get("/webhook")]
-pub async fn webhook_verify(web_query: web::Query<WebQuery>) -> HttpResponse {
-    web_query.get_hub_challenge()
+pub async fn webhook_verify(
+    web_query: web::Query<WebQuery>,
+    app_state: web::Data<App>,
+) -> HttpResponse {
+    web_query.get_hub_challenge(app_state.verify_token.as_deref())
+}
+
+/// Returns the user id recorded in the spans, hashed if `App::hash_user_ids` is set.
+fn traced_user_id(app_state: &App, user: &str) -> String {
+    if app_state.hash_user_ids {
+        hex::encode(&Sha256::digest(user.as_bytes())[..8])
+    } else {
+        user.to_owned()
+    }
 }
 
 #[post("/webhook")]
+#[tracing::instrument(name = "webhook", skip_all)]
 pub async fn webhook_core(
-    data: web::Json<InComingData>,
+    request: HttpRequest,
+    body: web::Bytes,
     app_state: web::Data<App>,
     conn: dev::ConnectionInfo,
 ) -> HttpResponse {
-    let query = app_state.query.clone();
-    let router = app_state.router.clone();
-    let version = app_state.facebook_api_version.clone();
-    let token = app_state.page_access_token.clone();
+    if app_state.verify_signature {
+        let Some(app_secret) = &app_state.app_secret else {
+            warn!("Rejected a webhook event, no App Secret is set to verify its signature");
+            return HttpResponse::Forbidden().body("Invalid signature");
+        };
+        let signature = request
+            .headers()
+            .get(SIGNATURE_HEADER)
+            .and_then(|value| value.to_str().ok());
+        if !verify_signature(app_secret, &body, signature) {
+            warn!("Rejected a webhook event with an invalid signature");
+            return HttpResponse::Forbidden().body("Invalid signature");
+        }
+    }
+
+    let data: InComingData = match serde_json::from_slice(&body) {
+        Ok(data) => data,
+        Err(err) => {
+            warn!(error = %err, "Rejected an invalid webhook event");
+            return HttpResponse::BadRequest().body(err.to_string());
+        }
+    };
+    let Some(dispatcher) = &app_state.dispatcher else {
+        return HttpResponse::ServiceUnavailable().body("Workers are not running");
+    };
 
-    let user = data.get_sender();
     let host = conn.host();
+    let mut dispatched = true;
+    for (page_id, user, events) in data.group_by_sender() {
+        if !app_state.pages.contains_key(&page_id) && !app_state.graph.has_page_access_token() {
+            warn!(
+                page_id,
+                "Ignoring the events of a page that is not registered"
+            );
+            continue;
+        }
 
-    query.create(user).await.unwrap();
-
-    if app_state.action_lock.lock(user).await {
-        if let Some(message) = data.get_message() {
-            if let Some(quick_reply) = message.get_quick_reply() {
-                let quick_reply_payload = quick_reply.get_payload();
-                let payload =
-                    Message::Payload(user, quick_reply_payload, host, query, version, token);
-                let result = handle(payload, router).await;
-                result.unwrap_or_else(|err| {
-                    eprintln!("Error handling quick reply payload: {:?}", err)
-                })
-            } else {
-                let text = message.get_text();
-                let text_message = Message::TextMessage(user, &text, host, query, version, token);
-                let result = handle(text_message, router).await;
-                result.unwrap_or_else(|err| eprintln!("Error handling text message: {:?}", err))
-            }
-        } else if let Some(postback) = data.get_postback() {
-            let postback_payload = postback.get_payload();
-            let payload = Message::Payload(user, postback_payload, host, query, version, token);
-            let result = handle(payload, router).await;
-            result.unwrap_or_else(|err| eprintln!("Error handling postback payload: {:?}", err))
+        let traced_user_id = traced_user_id(&app_state, &user);
+        let user = UserKey { page_id, user };
+        let mut schedule = false;
+        for messaging in events {
+            metrics::event_received(&messaging);
+            let span = info_span!(
+                "event",
+                user = %traced_user_id,
+                page_id = %user.page_id,
+                kind = messaging.kind(),
+            );
+            let event = Event {
+                messaging,
+                host: host.to_owned(),
+                span,
+            };
+            schedule |= app_state.user_queues.push(&user, event).await;
+        }
+        if schedule && dispatcher.send(Job::Run { user: user.clone() }).is_err() {
+            // The workers are stopped: release the user, or their next events would never be scheduled.
+            let dropped = app_state.user_queues.release(&user).await;
+            error!(
+                page_id = %user.page_id,
+                dropped,
+                "Error dispatching user events, the workers are stopped"
+            );
+            dispatched = false;
         }
-        app_state.action_lock.unlock(user).await;
     }
 
+    if !dispatched {
+        return HttpResponse::ServiceUnavailable().body("Workers are not running");
+    }
     HttpResponse::Ok().finish()
 }
+
+/// Returns the database and the Graph API client of the users of `page_id`.
+///
+/// The users of a page registered with `App::page` are scoped to it, the others belong to the default page.
+pub(crate) fn page_context(app_state: &App, page_id: &str) -> (Arc<Query>, GraphClient) {
+    match app_state.pages.get(page_id) {
+        Some(page) => (
+            Arc::new(app_state.query.for_page(page_id)),
+            app_state
+                .graph
+                .clone()
+                .page_access_token(&page.page_access_token),
+        ),
+        None => (app_state.query.clone(), app_state.graph.clone()),
+    }
+}
+
+pub(crate) async fn handle_user_events(app_state: &App, user: &UserKey) {
+    let (query, graph) = page_context(app_state, &user.page_id);
+    if let Err(err) = query.create(&user.user).await {
+        error!(error = %err, "Error creating user");
+    }
+
+    while let Some(event) = app_state.user_queues.next(user).await {
+        let context = Context {
+            user,
+            host: &event.host,
+            query: query.clone(),
+            graph: graph.clone(),
+            payload_secret: app_state.payload_secret.clone(),
+        };
+        handle_event(&app_state.router, &context, event.messaging)
+            .instrument(event.span)
+            .await;
+    }
+}
+
+async fn handle_event(router: &Router, context: &Context<'_>, event: Messaging) {
+    if let Some(message) = event.get_message() {
+        if let Some(quick_reply) = message.get_quick_reply() {
+            let quick_reply_payload = quick_reply.get_payload();
+            let payload = Message::Payload(quick_reply_payload);
+            let result = handle(payload, context, router).await;
+            result.unwrap_or_else(|err| error!(error = %err, "Error handling quick reply payload"))
+        } else {
+            let text = message.get_text();
+            let attachments = message.get_attachments();
+            let text_message = Message::TextMessage(&text, attachments);
+            let result = handle(text_message, context, router).await;
+            result.unwrap_or_else(|err| error!(error = %err, "Error handling text message"))
+        }
+    } else if let Some(postback) = event.get_postback() {
+        let postback_payload = postback.get_payload();
+        let payload = Message::Payload(postback_payload);
+        let result = handle(payload, context, router).await;
+        result.unwrap_or_else(|err| error!(error = %err, "Error handling postback payload"))
+    }
+}
@@ -0,0 +1,122 @@
+//! The `health` module contains the liveness and readiness endpoints, for load balancers and orchestrators like Kubernetes.
+//!
+//! * `GET /healthz` answers `200 OK` as long as the server is running.
+//! * `GET /readyz` answers `200 OK` when the workers are running, the database can be reached through `Query` and the
+//!   page access tokens are valid, and `503 Service Unavailable` otherwise, or as soon as the app is shutting down.
+//!   The JSON body reports every check.
+//!
+//! The tokens of the pages are checked concurrently, and a valid token is only checked again after five minutes, so
+//! that frequent probes don't hit the Graph API.
+use std::{
+    collections::HashMap,
+    sync::{atomic::Ordering, Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use actix_web::{get, web, HttpResponse};
+use serde_json::{Map, Value};
+use tokio::task::JoinSet;
+
+use crate::App;
+
+/// How long a valid page access token is not checked again.
+const TOKEN_CHECK_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// The time until which the token of each page is known to be valid.
+#[derive(Clone, Default)]
+pub(crate) struct TokenChecks {
+    valid_until: Arc<Mutex<HashMap<String, Instant>>>,
+}
+
+impl TokenChecks {
+    fn is_valid(&self, page_id: &str) -> bool {
+        let valid_until = self.valid_until.lock().unwrap();
+        valid_until
+            .get(page_id)
+            .is_some_and(|valid_until| Instant::now() < *valid_until)
+    }
+
+    fn set_valid(&self, page_id: &str) {
+        let mut valid_until = self.valid_until.lock().unwrap();
+        valid_until.insert(page_id.to_owned(), Instant::now() + TOKEN_CHECK_TTL);
+    }
+}
+
+#[get("/healthz")]
+pub async fn healthz() -> HttpResponse {
+    HttpResponse::Ok().body("OK")
+}
+
+#[get("/readyz")]
+pub async fn readyz(app_state: web::Data<App>) -> HttpResponse {
+    let workers = match app_state.dispatcher {
+        _ if app_state.shutting_down.load(Ordering::Relaxed) => Err("Shutting down".to_owned()),
+        Some(_) => Ok(()),
+        None => Err("Workers are not running".to_owned()),
+    };
+    let database = app_state.query.ping().await.map_err(|err| err.to_string());
+    let page_token = check_tokens(&app_state).await;
+
+    let checks = [
+        ("workers", workers),
+        ("database", database),
+        ("page_token", page_token),
+    ];
+    let ready = checks.iter().all(|(_, result)| result.is_ok());
+    let body: Map<String, Value> = checks
+        .into_iter()
+        .map(|(name, result)| {
+            let status = result.err().unwrap_or_else(|| "ok".to_owned());
+            (name.to_owned(), Value::String(status))
+        })
+        .collect();
+
+    if ready {
+        HttpResponse::Ok().json(body)
+    } else {
+        HttpResponse::ServiceUnavailable().json(body)
+    }
+}
+
+/// Checks the token of the default page, if it is set, and the tokens of the registered pages, concurrently.
+///
+/// Returns the error of the first page failing, in the order of their ids, the default page first.
+async fn check_tokens(app_state: &App) -> Result<(), String> {
+    let default = app_state
+        .graph
+        .has_page_access_token()
+        .then(|| (String::new(), app_state.graph.clone()));
+    let pages = app_state.pages.iter().map(|(page_id, page)| {
+        let graph = app_state
+            .graph
+            .clone()
+            .page_access_token(&page.page_access_token);
+        (page_id.clone(), graph)
+    });
+
+    let mut checks = JoinSet::new();
+    for (page_id, graph) in default.into_iter().chain(pages) {
+        if !app_state.token_checks.is_valid(&page_id) {
+            checks.spawn(async move { (page_id, graph.check_token().await) });
+        }
+    }
+
+    let mut errors = Vec::new();
+    while let Some(check) = checks.join_next().await {
+        match check {
+            Ok((page_id, Ok(()))) => app_state.token_checks.set_valid(&page_id),
+            Ok((page_id, Err(err))) if page_id.is_empty() => {
+                errors.push((page_id, err.to_string()))
+            }
+            Ok((page_id, Err(err))) => {
+                errors.push((page_id.clone(), format!("Page {page_id}: {err}")))
+            }
+            Err(err) => errors.push((String::new(), err.to_string())),
+        }
+    }
+    errors.sort();
+    errors
+        .into_iter()
+        .next()
+        .map_or(Ok(()), |(_, err)| Err(err))
+}
@@ -1,8 +1,17 @@
 pub mod handlers;
+pub mod health;
+pub mod queue;
+pub(crate) mod signature;
+pub mod worker;
+
+use std::io;
 
 use actix_web::HttpResponse;
 use serde::{Deserialize, Serialize};
 
+use crate::core::response::Res;
+use crate::error::Result;
+
 #[derive(Debug, Deserialize)]
 pub struct WebQuery {
     #[serde(rename = "hub.mode")]
@@ -14,14 +23,15 @@ pub struct WebQuery {
 }
 
 impl WebQuery {
-    pub fn get_hub_challenge(&self) -> HttpResponse {
+    /// Answers the verification request of Facebook, when its token is equal to `verify_token`.
+    pub fn get_hub_challenge(&self, verify_token: Option<&str>) -> HttpResponse {
         match (
             self.hub_mode.clone(),
             self.hub_challenge.clone(),
             self.hub_verify_token.clone(),
         ) {
             (Some(hub_mode), Some(hub_challenge), Some(token)) => {
-                if hub_mode.eq("subscribe") && std::env::var("VERIFY_TOKEN").unwrap().eq(&token) {
+                if hub_mode.eq("subscribe") && verify_token == Some(token.as_str()) {
                     HttpResponse::Ok().body(hub_challenge)
                 } else {
                     HttpResponse::Unauthorized().body("Token mismatch")
@@ -48,10 +58,107 @@ impl QuickReplyPayload {
     }
 }
 
+/// The type of an attachment sent by the user.
+///
+/// Types that are not known by russenger are deserialized as `Other`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AttachmentType {
+    Image,
+    Audio,
+    Video,
+    File,
+    Location,
+    Fallback,
+    #[default]
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize)]
+pub struct Coordinates {
+    pub lat: f64,
+    pub long: f64,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct AttachmentPayload {
+    pub url: Option<String>,
+    pub title: Option<String>,
+    pub sticker_id: Option<u64>,
+    pub coordinates: Option<Coordinates>,
+}
+
+/// `Attachment` represents a file, a sticker or a location sent by the user.
+///
+/// # Examples
+///
+/// Replying to the user according to what they sent:
+///
+/// ```rust
+/// use russenger::prelude::*;
+///
+/// async fn index(res: Res, req: Req) -> Result<()> {
+///     for attachment in &req.attachments {
+///         match attachment.r#type {
+///             AttachmentType::Image if attachment.payload.sticker_id.is_some() => {
+///                 res.send(TextModel::new(&req.user, "Nice sticker!")).await?;
+///             }
+///             AttachmentType::Image => {
+///                 let bytes = attachment.download(&res).await?;
+///                 res.send(TextModel::new(&req.user, format!("Image of {} bytes", bytes.len()))).await?;
+///             }
+///             AttachmentType::Location => {
+///                 if let Some(coordinates) = attachment.get_coordinates() {
+///                     res.send(TextModel::new(&req.user, format!("You are at {}, {}", coordinates.lat, coordinates.long))).await?;
+///                 }
+///             }
+///             _ => {}
+///         }
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct Attachment {
+    #[serde(rename = "type")]
+    pub r#type: AttachmentType,
+    #[serde(default)]
+    pub payload: AttachmentPayload,
+}
+
+impl Attachment {
+    pub fn get_url(&self) -> Option<&String> {
+        self.payload.url.as_ref()
+    }
+
+    pub fn get_coordinates(&self) -> Option<Coordinates> {
+        self.payload.coordinates
+    }
+
+    /// Downloads the content of the attachment from its payload URL.
+    ///
+    /// The download shares the connection pool and the timeout of the Graph API client of `res`, so a stalled download
+    /// can't block the worker running the action.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the attachment has no URL, like a location, or if the download fails or times out.
+    pub async fn download(&self, res: &Res) -> Result<Vec<u8>> {
+        let url = self.get_url().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, "Attachment has no URL to download")
+        })?;
+        Ok(res.graph().download(url).await?)
+    }
+}
+
 #[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct Message {
     pub text: Option<String>,
     pub quick_reply: Option<QuickReplyPayload>,
+    #[serde(default)]
+    pub attachments: Vec<Attachment>,
 }
 
 impl Message {
@@ -62,6 +169,10 @@ impl Message {
     pub fn get_quick_reply(&self) -> Option<QuickReplyPayload> {
         self.quick_reply.clone()
     }
+
+    pub fn get_attachments(&self) -> Vec<Attachment> {
+        self.attachments.clone()
+    }
 }
 
 #[derive(Debug, Clone, Default, Deserialize, Serialize)]
@@ -82,26 +193,195 @@ pub struct Messaging {
     pub message: Option<Message>,
 }
 
+impl Messaging {
+    pub fn get_sender(&self) -> &String {
+        &self.sender.id
+    }
+
+    pub fn get_message(&self) -> Option<Message> {
+        self.message.clone()
+    }
+
+    pub fn get_postback(&self) -> Option<Postback> {
+        self.postback.clone()
+    }
+
+    /// The kind of the event: `text`, `quick_reply`, `attachment`, `postback` or `other`.
+    ///
+    /// A message is an `attachment` when it only carries attachments, without text.
+    pub fn kind(&self) -> &'static str {
+        match (&self.message, &self.postback) {
+            (Some(message), _) if message.quick_reply.is_some() => "quick_reply",
+            (Some(message), _)
+                if message.get_text().is_empty() && !message.attachments.is_empty() =>
+            {
+                "attachment"
+            }
+            (Some(_), _) => "text",
+            (None, Some(_)) => "postback",
+            (None, None) => "other",
+        }
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Entry {
+    /// The id of the page the events were sent to.
+    #[serde(default)]
+    pub id: String,
+    #[serde(default)]
     pub messaging: Vec<Messaging>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct InComingData {
+    #[serde(default)]
     pub entry: Vec<Entry>,
 }
 
 impl InComingData {
-    pub fn get_sender(&self) -> &String {
-        &self.entry[0].messaging[0].sender.id
+    /// Groups every messaging event of every entry by page and sender.
+    ///
+    /// Facebook may batch several events, even of several pages, in a single webhook call. The
+    /// `(page_id, sender)` pairs are returned in the order they first appear, and the events of
+    /// each pair keep their delivery order.
+    pub fn group_by_sender(self) -> Vec<(String, String, Vec<Messaging>)> {
+        let mut groups: Vec<(String, String, Vec<Messaging>)> = Vec::new();
+        for entry in self.entry {
+            for messaging in entry.messaging {
+                match groups.iter_mut().find(|(page_id, sender, _)| {
+                    *page_id == entry.id && sender == messaging.get_sender()
+                }) {
+                    Some((_, _, events)) => events.push(messaging),
+                    None => groups.push((
+                        entry.id.clone(),
+                        messaging.get_sender().clone(),
+                        vec![messaging],
+                    )),
+                }
+            }
+        }
+        groups
     }
+}
 
-    pub fn get_message(&self) -> Option<Message> {
-        self.entry[0].messaging[0].message.clone()
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn text(sender: &str, text: &str) -> serde_json::Value {
+        json!({ "sender": { "id": sender }, "message": { "text": text } })
     }
 
-    pub fn get_postback(&self) -> Option<Postback> {
-        self.entry[0].messaging[0].postback.clone()
+    fn attachment(attachment: serde_json::Value) -> Attachment {
+        serde_json::from_value(attachment).unwrap()
+    }
+
+    #[test]
+    fn deserializes_images_and_stickers() {
+        let image = attachment(json!({
+            "type": "image",
+            "payload": { "url": "https://example.com/image.png" }
+        }));
+        assert_eq!(image.r#type, AttachmentType::Image);
+        assert_eq!(
+            image.get_url().map(String::as_str),
+            Some("https://example.com/image.png")
+        );
+        assert_eq!(image.payload.sticker_id, None);
+
+        let sticker = attachment(json!({
+            "type": "image",
+            "payload": { "url": "https://example.com/sticker.png", "sticker_id": 369239263222822u64 }
+        }));
+        assert_eq!(sticker.payload.sticker_id, Some(369239263222822));
+    }
+
+    #[test]
+    fn deserializes_locations() {
+        let location = attachment(json!({
+            "type": "location",
+            "payload": { "coordinates": { "lat": -18.91, "long": 47.52 } }
+        }));
+        assert_eq!(location.r#type, AttachmentType::Location);
+        let coordinates = location.get_coordinates().unwrap();
+        assert_eq!((coordinates.lat, coordinates.long), (-18.91, 47.52));
+        assert_eq!(location.get_url(), None);
+    }
+
+    #[test]
+    fn deserializes_fallbacks_and_unknown_types() {
+        let fallback = attachment(json!({
+            "type": "fallback",
+            "payload": { "url": "https://example.com", "title": "Example" }
+        }));
+        assert_eq!(fallback.r#type, AttachmentType::Fallback);
+        assert_eq!(fallback.payload.title.as_deref(), Some("Example"));
+
+        let unknown = attachment(json!({ "type": "ig_reel" }));
+        assert_eq!(unknown.r#type, AttachmentType::Other);
+        assert_eq!(unknown.get_url(), None);
+    }
+
+    #[test]
+    fn messages_with_only_attachments_are_attachments() {
+        let messaging = |message: serde_json::Value| -> Messaging {
+            serde_json::from_value(json!({ "sender": { "id": "alice" }, "message": message }))
+                .unwrap()
+        };
+        let image =
+            json!({ "type": "image", "payload": { "url": "https://example.com/image.png" } });
+
+        assert_eq!(
+            messaging(json!({ "attachments": [image] })).kind(),
+            "attachment"
+        );
+        assert_eq!(
+            messaging(json!({ "text": "Look", "attachments": [image] })).kind(),
+            "text"
+        );
+        assert_eq!(messaging(json!({ "text": "Hi" })).kind(), "text");
+        assert_eq!(
+            messaging(json!({ "text": "Yes", "quick_reply": { "payload": "{}" } })).kind(),
+            "quick_reply"
+        );
+    }
+
+    #[test]
+    fn events_are_grouped_by_page_and_sender_in_order() {
+        let data: InComingData = serde_json::from_value(json!({
+            "entry": [
+                { "id": "page_1", "messaging": [text("alice", "1"), text("bob", "1")] },
+                { "id": "page_2", "messaging": [text("alice", "1")] },
+                { "id": "page_1", "messaging": [text("alice", "2")] },
+            ]
+        }))
+        .unwrap();
+
+        let groups: Vec<_> = data
+            .group_by_sender()
+            .into_iter()
+            .map(|(page_id, sender, events)| {
+                let texts: Vec<_> = events
+                    .iter()
+                    .map(|messaging| messaging.get_message().unwrap().get_text())
+                    .collect();
+                (page_id, sender, texts)
+            })
+            .collect();
+        assert_eq!(
+            groups,
+            [
+                (
+                    "page_1".into(),
+                    "alice".into(),
+                    vec!["1".to_owned(), "2".to_owned()]
+                ),
+                ("page_1".into(), "bob".into(), vec!["1".to_owned()]),
+                ("page_2".into(), "alice".into(), vec!["1".to_owned()]),
+            ]
+        );
     }
 }
@@ -0,0 +1,258 @@
+//! The `queue` module keeps a bounded FIFO queue of pending events for every user.
+//!
+//! Events of the same user are processed in order, one at a time, while events of different users
+//! are processed in parallel. When a user sends a new event while one of their actions is still
+//! running, the event is queued instead of being dropped.
+//!
+//! # Examples
+//!
+//! Configuring the size of the queue and what happens when it is full:
+//!
+//! ```rust,no_run
+//! use russenger::prelude::*;
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<()> {
+//!     App::init().await?
+//!         .queue_capacity(10)
+//!         .overflow_policy(OverflowPolicy::DropOldest)
+//!         .attach(router![("/", |res: Res, req: Req| async move {
+//!             res.send(TextModel::new(&req.user, "Hello")).await?;
+//!             Ok(())
+//!         })])
+//!         .launch()
+//!         .await?;
+//!     Ok(())
+//! }
+//! ```
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Arc,
+};
+
+use tokio::sync::Mutex;
+use tracing::{warn, Span};
+
+use crate::metrics;
+
+use super::Messaging;
+
+/// The default number of events that can wait in the queue of a single user.
+pub const DEFAULT_QUEUE_CAPACITY: usize = 100;
+
+/// `OverflowPolicy` specifies what to do with a new event when the queue of its user is full.
+///
+/// # Variants
+///
+/// * `DropNewest` - Discards the incoming event and keeps the queued ones.
+/// * `DropOldest` - Discards the oldest queued event to make room for the incoming one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    #[default]
+    DropNewest,
+    DropOldest,
+}
+
+/// A messaging event waiting to be processed, along with the host it was received on and its span.
+pub(crate) struct Event {
+    pub(crate) messaging: Messaging,
+    pub(crate) host: String,
+    pub(crate) span: Span,
+}
+
+/// A user of a page, the same user has a separate queue on every page.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct UserKey {
+    pub(crate) page_id: String,
+    pub(crate) user: String,
+}
+
+#[derive(Clone)]
+pub(crate) struct UserQueues {
+    pending: Arc<Mutex<HashMap<UserKey, VecDeque<Event>>>>,
+    pub(crate) capacity: usize,
+    pub(crate) overflow_policy: OverflowPolicy,
+}
+
+impl Default for UserQueues {
+    fn default() -> Self {
+        Self {
+            pending: Arc::default(),
+            capacity: DEFAULT_QUEUE_CAPACITY,
+            overflow_policy: OverflowPolicy::default(),
+        }
+    }
+}
+
+impl UserQueues {
+    /// Adds an event to the queue of `user`.
+    ///
+    /// Returns `true` when no action of `user` is running or scheduled: the caller must then
+    /// schedule `user` so that its queue gets drained with `next`.
+    pub(crate) async fn push(&self, user: &UserKey, event: Event) -> bool {
+        let mut pending = self.pending.lock().await;
+        let Some(queue) = pending.get_mut(user) else {
+            pending.insert(user.clone(), VecDeque::from([event]));
+            return true;
+        };
+
+        if queue.len() < self.capacity {
+            queue.push_back(event);
+            metrics::event_waiting();
+            return false;
+        }
+
+        // The span of the event carries the user, hashed when `App::hash_user_ids` is set.
+        metrics::event_dropped();
+        match self.overflow_policy {
+            OverflowPolicy::DropNewest => event.span.in_scope(|| {
+                warn!("Queue of user is full, dropping the newest event");
+            }),
+            OverflowPolicy::DropOldest => {
+                event.span.in_scope(|| {
+                    warn!("Queue of user is full, dropping the oldest event");
+                });
+                queue.pop_front();
+                queue.push_back(event);
+            }
+        }
+        false
+    }
+
+    /// Takes the next queued event of `user`.
+    ///
+    /// Returns `None` and releases `user` once the queue is empty, so that the next pushed event
+    /// schedules `user` again.
+    pub(crate) async fn next(&self, user: &UserKey) -> Option<Event> {
+        let mut pending = self.pending.lock().await;
+        let event = pending.get_mut(user).and_then(VecDeque::pop_front);
+        if event.is_none() {
+            pending.remove(user);
+        }
+        event
+    }
+
+    /// Drops the queued events of `user` and releases it, when it can't be scheduled.
+    ///
+    /// Returns the number of dropped events.
+    pub(crate) async fn release(&self, user: &UserKey) -> usize {
+        let mut pending = self.pending.lock().await;
+        pending.remove(user).map_or(0, |queue| queue.len())
+    }
+
+    /// Returns `true` when no user has an event queued or being processed.
+    #[cfg(feature = "testing")]
+    pub(crate) async fn is_idle(&self) -> bool {
+        self.pending.lock().await.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::Message;
+
+    fn user(user: &str) -> UserKey {
+        UserKey {
+            page_id: "page_id".to_owned(),
+            user: user.to_owned(),
+        }
+    }
+
+    fn event(text: &str) -> Event {
+        let message = Message {
+            text: Some(text.to_owned()),
+            ..Message::default()
+        };
+        Event {
+            messaging: Messaging {
+                message: Some(message),
+                ..Messaging::default()
+            },
+            host: "localhost".to_owned(),
+            span: Span::none(),
+        }
+    }
+
+    fn queues(capacity: usize, overflow_policy: OverflowPolicy) -> UserQueues {
+        UserQueues {
+            capacity,
+            overflow_policy,
+            ..UserQueues::default()
+        }
+    }
+
+    async fn drain(queues: &UserQueues, user: &UserKey) -> Vec<String> {
+        let mut texts = Vec::new();
+        while let Some(event) = queues.next(user).await {
+            texts.push(event.messaging.message.unwrap().text.unwrap());
+        }
+        texts
+    }
+
+    #[tokio::test]
+    async fn only_the_first_event_schedules_the_user() {
+        let queues = UserQueues::default();
+
+        assert!(queues.push(&user("alice"), event("1")).await);
+        assert!(!queues.push(&user("alice"), event("2")).await);
+        assert!(queues.push(&user("bob"), event("1")).await);
+    }
+
+    #[tokio::test]
+    async fn events_of_a_user_are_taken_in_order() {
+        let queues = UserQueues::default();
+        for text in ["1", "2", "3"] {
+            queues.push(&user("alice"), event(text)).await;
+        }
+        queues.push(&user("bob"), event("4")).await;
+
+        assert_eq!(drain(&queues, &user("alice")).await, ["1", "2", "3"]);
+        assert_eq!(drain(&queues, &user("bob")).await, ["4"]);
+    }
+
+    #[tokio::test]
+    async fn an_empty_queue_releases_the_user() {
+        let queues = UserQueues::default();
+        queues.push(&user("alice"), event("1")).await;
+
+        assert!(queues.next(&user("alice")).await.is_some());
+        assert!(!queues.push(&user("alice"), event("2")).await);
+        assert!(queues.next(&user("alice")).await.is_some());
+        assert!(queues.next(&user("alice")).await.is_none());
+        assert!(queues.pending.lock().await.is_empty());
+        assert!(queues.push(&user("alice"), event("3")).await);
+    }
+
+    #[tokio::test]
+    async fn a_released_user_is_scheduled_again() {
+        let queues = UserQueues::default();
+        queues.push(&user("alice"), event("1")).await;
+        queues.push(&user("alice"), event("2")).await;
+
+        assert_eq!(queues.release(&user("alice")).await, 2);
+        assert_eq!(queues.release(&user("alice")).await, 0);
+        assert!(queues.push(&user("alice"), event("3")).await);
+        assert_eq!(drain(&queues, &user("alice")).await, ["3"]);
+    }
+
+    #[tokio::test]
+    async fn a_full_queue_drops_the_newest_event() {
+        let queues = queues(2, OverflowPolicy::DropNewest);
+        for text in ["0", "1", "2", "3"] {
+            queues.push(&user("alice"), event(text)).await;
+        }
+
+        assert_eq!(drain(&queues, &user("alice")).await, ["0", "1"]);
+    }
+
+    #[tokio::test]
+    async fn a_full_queue_drops_the_oldest_event() {
+        let queues = queues(2, OverflowPolicy::DropOldest);
+        for text in ["0", "1", "2", "3"] {
+            queues.push(&user("alice"), event(text)).await;
+        }
+
+        assert_eq!(drain(&queues, &user("alice")).await, ["2", "3"]);
+    }
+}
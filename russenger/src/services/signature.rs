@@ -0,0 +1,106 @@
+//! Verification of the `X-Hub-Signature-256` header sent by Facebook with every webhook event.
+//!
+//! Facebook signs the raw request body with the App Secret using HMAC-SHA256 and sends the
+//! hex digest prefixed by `sha256=`. Requests whose signature is missing or does not match are
+//! rejected before the body is deserialized.
+//!
+//! ## Reference
+//!
+//! [Facebook Messenger Platform - Webhooks Security](https://developers.facebook.com/docs/messenger-platform/webhooks#security)
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+pub(crate) const SIGNATURE_HEADER: &str = "X-Hub-Signature-256";
+
+const SIGNATURE_PREFIX: &str = "sha256=";
+
+/// Checks that `signature` is the HMAC-SHA256 of `body` keyed with `app_secret`.
+///
+/// The comparison is done in constant time.
+pub(crate) fn verify_signature(app_secret: &str, body: &[u8], signature: Option<&str>) -> bool {
+    let Some(digest) = signature.and_then(|signature| signature.strip_prefix(SIGNATURE_PREFIX))
+    else {
+        return false;
+    };
+    let Ok(expected) = hex::decode(digest) else {
+        return false;
+    };
+    let Ok(mut mac) = Hmac::<Sha256>::new_from_slice(app_secret.as_bytes()) else {
+        return false;
+    };
+    mac.update(body);
+    mac.verify_slice(&expected).is_ok()
+}
+
+/// Computes the `X-Hub-Signature-256` header of `body`, as Facebook does.
+#[cfg(any(test, feature = "testing"))]
+pub(crate) fn sign(app_secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(app_secret.as_bytes()).expect("HMAC accepts any key size");
+    mac.update(body);
+    format!(
+        "{SIGNATURE_PREFIX}{}",
+        hex::encode(mac.finalize().into_bytes())
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const APP_SECRET: &str = "app_secret";
+    const BODY: &[u8] = br#"{"object":"page","entry":[]}"#;
+
+    #[test]
+    fn accepts_a_good_signature() {
+        let signature = sign(APP_SECRET, BODY);
+        assert!(verify_signature(APP_SECRET, BODY, Some(&signature)));
+    }
+
+    #[test]
+    fn rejects_a_bad_signature() {
+        let signature = sign("another_secret", BODY);
+        assert!(!verify_signature(APP_SECRET, BODY, Some(&signature)));
+
+        let signature = sign(APP_SECRET, BODY);
+        assert!(!verify_signature(APP_SECRET, b"{}", Some(&signature)));
+    }
+
+    #[test]
+    fn rejects_a_missing_signature() {
+        assert!(!verify_signature(APP_SECRET, BODY, None));
+    }
+
+    #[test]
+    fn rejects_a_malformed_prefix() {
+        let digest = sign(APP_SECRET, BODY).replacen(SIGNATURE_PREFIX, "", 1);
+        assert!(!verify_signature(APP_SECRET, BODY, Some(&digest)));
+        assert!(!verify_signature(
+            APP_SECRET,
+            BODY,
+            Some(&format!("sha1={digest}"))
+        ));
+        assert!(!verify_signature(
+            APP_SECRET,
+            BODY,
+            Some(&format!("SHA256={digest}"))
+        ));
+        assert!(!verify_signature(APP_SECRET, BODY, Some("sha256=not-hex")));
+    }
+
+    #[test]
+    fn rejects_a_digest_of_the_wrong_length() {
+        let signature = sign(APP_SECRET, BODY);
+        assert!(!verify_signature(
+            APP_SECRET,
+            BODY,
+            Some(&signature[..signature.len() - 2])
+        ));
+        assert!(!verify_signature(
+            APP_SECRET,
+            BODY,
+            Some(&format!("{signature}00"))
+        ));
+        assert!(!verify_signature(APP_SECRET, BODY, Some(SIGNATURE_PREFIX)));
+    }
+}
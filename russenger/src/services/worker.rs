@@ -0,0 +1,105 @@
+//! The `worker` module runs the actions of the application on a pool of background tasks.
+//!
+//! The webhook endpoint only queues the incoming events and acknowledges them right away, so that
+//! slow actions never make Facebook time out. Each worker takes a user whose queue has pending
+//! events and processes them in order until the queue is empty.
+//!
+//! The pool also deletes the expired sessions and payloads of the database every `CLEANUP_INTERVAL`.
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use tokio::{
+    sync::{mpsc, Mutex},
+    task::JoinHandle,
+};
+
+use crate::{db::Query, App};
+
+use super::{handlers::handle_user_events, queue::UserKey};
+
+/// The default number of workers processing the queued events.
+pub const DEFAULT_WORKERS: usize = 4;
+
+/// How often the expired sessions and payloads are deleted.
+pub const CLEANUP_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+pub(crate) enum Job {
+    Run { user: UserKey },
+    Stop,
+}
+
+pub(crate) type Dispatcher = mpsc::UnboundedSender<Job>;
+
+/// `WorkerPool` is the handle of the workers started by [`App::start_workers`].
+pub struct WorkerPool {
+    dispatcher: Dispatcher,
+    handles: Vec<JoinHandle<()>>,
+    cleanup: JoinHandle<()>,
+    shutting_down: Arc<AtomicBool>,
+}
+
+impl WorkerPool {
+    /// Spawns `workers` tasks consuming the jobs sent through the pool's dispatcher.
+    pub(crate) fn start(app: &App, workers: usize) -> Self {
+        let (dispatcher, receiver) = mpsc::unbounded_channel();
+        let receiver = Arc::new(Mutex::new(receiver));
+        let handles = (0..workers.max(1))
+            .map(|_| tokio::spawn(work(app.clone(), receiver.clone())))
+            .collect();
+        let cleanup = tokio::spawn(clean_up(app.query.clone()));
+        Self {
+            dispatcher,
+            handles,
+            cleanup,
+            shutting_down: app.shutting_down.clone(),
+        }
+    }
+
+    pub(crate) fn dispatcher(&self) -> Dispatcher {
+        self.dispatcher.clone()
+    }
+
+    /// Waits for every job already dispatched to be processed, then stops the workers.
+    ///
+    /// `/readyz` answers `503 Service Unavailable` from the moment this method is called.
+    pub async fn shutdown(self) {
+        self.shutting_down.store(true, Ordering::Relaxed);
+        self.cleanup.abort();
+        for _ in &self.handles {
+            let _ = self.dispatcher.send(Job::Stop);
+        }
+        for handle in self.handles {
+            handle
+                .await
+                .unwrap_or_else(|err| tracing::error!(error = %err, "Error joining worker"));
+        }
+    }
+}
+
+async fn work(app: App, receiver: Arc<Mutex<mpsc::UnboundedReceiver<Job>>>) {
+    loop {
+        let job = receiver.lock().await.recv().await;
+        match job {
+            Some(Job::Run { user }) => handle_user_events(&app, &user).await,
+            Some(Job::Stop) | None => break,
+        }
+    }
+}
+
+async fn clean_up(query: Arc<Query>) {
+    let mut interval = tokio::time::interval(CLEANUP_INTERVAL);
+    loop {
+        interval.tick().await;
+        if let Err(err) = query.delete_expired_sessions().await {
+            tracing::warn!(error = %err, "Error deleting the expired sessions");
+        }
+        if let Err(err) = query.delete_expired_payloads().await {
+            tracing::warn!(error = %err, "Error deleting the expired payloads");
+        }
+    }
+}
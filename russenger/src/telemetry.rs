@@ -0,0 +1,135 @@
+//! The `telemetry` module installs a `tracing` subscriber for the logs and the spans of russenger.
+//!
+//! This module is only available with the `telemetry` feature, which is enabled by default.
+//!
+//! Russenger reports everything with `tracing`. Every webhook call opens a `webhook` span, and every event it contains
+//! an `event` span carrying the `user` (see [`App::hash_user_ids`](crate::App::hash_user_ids)), the `page_id` and the
+//! `kind` of the event. The actions run in an `action` span with their `route` and `path`, and every `Res::send` runs in
+//! a `graph.send` span recording the `endpoint` and the `message_id` or the `error` of the Graph API.
+//!
+//! `init` prints the logs to stdout, filtered with the `RUST_LOG` environment variable (`info` by default). Any other
+//! `tracing` subscriber can be installed instead. When no subscriber is installed, `App::launch` prints the logs to
+//! stderr, so that the errors of the actions are never lost.
+//!
+//! With the `opentelemetry` feature, `init` also exports the spans with OTLP over HTTP when the
+//! `OTEL_EXPORTER_OTLP_ENDPOINT` environment variable is set. The service name is read from `OTEL_SERVICE_NAME`, and is
+//! `russenger` by default.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use russenger::prelude::*;
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<()> {
+//!     // Keep the guard alive until the end, it flushes the spans when dropped
+//!     let _telemetry = russenger::telemetry::init()?;
+//!
+//!     App::init().await?
+//!         .hash_user_ids()
+//!         .attach(router![("/", |res: Res, req: Req| async move {
+//!             res.send(TextModel::new(&req.user, "Hello")).await?;
+//!             Ok(())
+//!         })])
+//!         .launch()
+//!         .await?;
+//!     Ok(())
+//! }
+//! ```
+use tracing_subscriber::{fmt, prelude::*, EnvFilter};
+
+use crate::error::Result;
+
+/// `Telemetry` is the guard returned by [`init`], it flushes the exported spans when dropped.
+pub struct Telemetry {
+    #[cfg(feature = "opentelemetry")]
+    provider: Option<opentelemetry_sdk::trace::SdkTracerProvider>,
+}
+
+/// Installs a global `tracing` subscriber printing the logs, and exporting the spans with the `opentelemetry` feature.
+///
+/// # Errors
+///
+/// Returns an error if a global subscriber is already installed, or if the OpenTelemetry exporter can't be built.
+pub fn init() -> Result<Telemetry> {
+    let registry = tracing_subscriber::registry()
+        .with(filter())
+        .with(fmt::layer());
+
+    #[cfg(feature = "opentelemetry")]
+    {
+        let provider = opentelemetry::provider()?;
+        let layer = provider.as_ref().map(opentelemetry::layer);
+        registry.with(layer).try_init()?;
+        Ok(Telemetry { provider })
+    }
+    #[cfg(not(feature = "opentelemetry"))]
+    {
+        registry.try_init()?;
+        Ok(Telemetry {})
+    }
+}
+
+/// Installs a global subscriber printing the logs to stderr, unless one is already installed.
+pub(crate) fn init_fallback() {
+    if !tracing::dispatcher::has_been_set() {
+        let subscriber = tracing_subscriber::registry()
+            .with(filter())
+            .with(fmt::layer().with_writer(std::io::stderr));
+        // Another subscriber may have been installed in the meantime, which is fine.
+        let _ = subscriber.try_init();
+    }
+}
+
+fn filter() -> EnvFilter {
+    EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"))
+}
+
+impl Drop for Telemetry {
+    fn drop(&mut self) {
+        #[cfg(feature = "opentelemetry")]
+        if let Some(provider) = self.provider.take() {
+            if let Err(err) = provider.shutdown() {
+                eprintln!("Error flushing the spans: {err}");
+            }
+        }
+    }
+}
+
+#[cfg(feature = "opentelemetry")]
+mod opentelemetry {
+    use std::env;
+
+    use opentelemetry::trace::TracerProvider;
+    use opentelemetry_otlp::SpanExporter;
+    use opentelemetry_sdk::{trace::SdkTracerProvider, Resource};
+    use tracing::Subscriber;
+    use tracing_subscriber::{registry::LookupSpan, Layer};
+
+    use crate::error::Result;
+
+    /// Builds the tracer provider exporting to `OTEL_EXPORTER_OTLP_ENDPOINT`, if it is set.
+    pub(super) fn provider() -> Result<Option<SdkTracerProvider>> {
+        if env::var_os("OTEL_EXPORTER_OTLP_ENDPOINT").is_none() {
+            return Ok(None);
+        }
+
+        let exporter = SpanExporter::builder().with_http().build()?;
+        let mut resource = Resource::builder();
+        if env::var_os("OTEL_SERVICE_NAME").is_none() {
+            resource = resource.with_service_name("russenger");
+        }
+        let provider = SdkTracerProvider::builder()
+            .with_batch_exporter(exporter)
+            .with_resource(resource.build())
+            .build();
+        Ok(Some(provider))
+    }
+
+    pub(super) fn layer<S>(provider: &SdkTracerProvider) -> impl Layer<S>
+    where
+        S: Subscriber + for<'span> LookupSpan<'span>,
+    {
+        tracing_opentelemetry::layer().with_tracer(provider.tracer("russenger"))
+    }
+}
@@ -0,0 +1,85 @@
+//! The `events` module builds synthetic webhook events, as Facebook would post them to `/webhook`.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use russenger::prelude::*;
+//! use russenger::testing::events;
+//!
+//! let hello = events::text("user_id", "Hello");
+//! let color = events::quick_reply("user_id", "blue", Payload::new("/color", Some(Data::new("blue"))));
+//! let start = events::postback("user_id", Payload::new("/start", None));
+//! let batch = events::batch([hello, color, events::on_page("1234567890", start)]);
+//! ```
+use crate::response_models::payload::Payload;
+use crate::services::{
+    Attachment, Entry, InComingData, Message, Messaging, Postback, QuickReplyPayload, Sender,
+};
+
+fn event(user: &str, message: Option<Message>, postback: Option<Postback>) -> InComingData {
+    InComingData {
+        entry: vec![Entry {
+            id: String::new(),
+            messaging: vec![Messaging {
+                sender: Sender {
+                    id: user.to_owned(),
+                },
+                postback,
+                message,
+            }],
+        }],
+    }
+}
+
+/// A text message sent by `user`.
+pub fn text(user: &str, text: &str) -> InComingData {
+    let message = Message {
+        text: Some(text.to_owned()),
+        ..Message::default()
+    };
+    event(user, Some(message), None)
+}
+
+/// A quick reply with the title `text` tapped by `user`.
+pub fn quick_reply(user: &str, text: &str, payload: Payload) -> InComingData {
+    let message = Message {
+        text: Some(text.to_owned()),
+        quick_reply: Some(QuickReplyPayload {
+            payload: payload.to_string(),
+        }),
+        ..Message::default()
+    };
+    event(user, Some(message), None)
+}
+
+/// A postback, from a button or the persistent menu, sent by `user`.
+pub fn postback(user: &str, payload: Payload) -> InComingData {
+    let postback = Postback {
+        payload: payload.to_string(),
+    };
+    event(user, None, Some(postback))
+}
+
+/// A message with `attachments` and no text sent by `user`.
+pub fn attachments(user: &str, attachments: impl IntoIterator<Item = Attachment>) -> InComingData {
+    let message = Message {
+        attachments: attachments.into_iter().collect(),
+        ..Message::default()
+    };
+    event(user, Some(message), None)
+}
+
+/// Sends the webhook events of `data` to the page `page_id`, instead of the default page.
+pub fn on_page(page_id: &str, mut data: InComingData) -> InComingData {
+    for entry in &mut data.entry {
+        entry.id = page_id.to_owned();
+    }
+    data
+}
+
+/// Merges several webhook events into a single batch, like Facebook does under load.
+pub fn batch(events: impl IntoIterator<Item = InComingData>) -> InComingData {
+    InComingData {
+        entry: events.into_iter().flat_map(|data| data.entry).collect(),
+    }
+}
@@ -0,0 +1,185 @@
+//! The `mock_graph` module contains `MockGraph`, a small Graph API server running in the test process.
+//!
+//! `MockGraph` listens on a random local port and records the body of every request posted by `Res`. It also answers
+//! the token checks of `/readyz` with a page.
+//! Point the application to it with `App::graph_api_url` or the `GRAPH_API_URL` environment variable.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use russenger::core::graph::GraphClient;
+//! use russenger::prelude::*;
+//! use russenger::testing::MockGraph;
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<()> {
+//!     let graph = MockGraph::start().await?;
+//!     let client = GraphClient::new("v19.0", "page_access_token").base_url(graph.url());
+//!
+//!     client.post("messages", &TextModel::new("user_id", "Hello")).await?;
+//!
+//!     let requests = graph.requests_to("user_id");
+//!     assert_eq!(requests[0].endpoint, "messages");
+//!     assert_eq!(requests[0].body["message"]["text"], "Hello");
+//!     Ok(())
+//! }
+//! ```
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+use actix_web::{dev::ServerHandle, get, post, web, App, HttpResponse, HttpServer};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+/// A request received by `MockGraph`.
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    pub version: String,
+    pub endpoint: String,
+    pub access_token: Option<String>,
+    pub body: Value,
+}
+
+impl RecordedRequest {
+    /// Returns the id of the recipient of the request, if any.
+    pub fn recipient(&self) -> Option<&str> {
+        self.body["recipient"]["id"].as_str()
+    }
+}
+
+#[derive(Default)]
+struct State {
+    requests: Mutex<Vec<RecordedRequest>>,
+    failures: Mutex<VecDeque<(u16, Value)>>,
+}
+
+/// `MockGraph` is an in-process stand-in for the Graph API.
+///
+/// Every request is recorded and answered like the Send API does: `messages` requests return a `recipient_id` and a
+/// `message_id`, and other endpoints return `{"result": "success"}`. The server stops when `MockGraph` is dropped.
+pub struct MockGraph {
+    url: String,
+    state: Arc<State>,
+    handle: ServerHandle,
+}
+
+impl MockGraph {
+    /// Starts the server on a random local port.
+    pub async fn start() -> std::io::Result<Self> {
+        let state = Arc::new(State::default());
+        let data = web::Data::from(state.clone());
+        let server = HttpServer::new(move || {
+            App::new()
+                .app_data(data.clone())
+                .service(page)
+                .service(graph)
+        })
+        .workers(1)
+        .bind(("127.0.0.1", 0))?;
+        let addr = server.addrs()[0];
+        let server = server.run();
+        let handle = server.handle();
+        tokio::spawn(server);
+
+        Ok(Self {
+            url: format!("http://{addr}"),
+            state,
+            handle,
+        })
+    }
+
+    /// The base URL of the server, to use with `App::graph_api_url`.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Returns every request received so far, in order.
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.state.requests.lock().unwrap().clone()
+    }
+
+    /// Returns the requests sent to `recipient`, in order.
+    pub fn requests_to(&self, recipient: &str) -> Vec<RecordedRequest> {
+        self.requests()
+            .into_iter()
+            .filter(|request| request.recipient() == Some(recipient))
+            .collect()
+    }
+
+    /// Answers the next request with `status` and `body` instead of a success.
+    ///
+    /// Failures are queued, so calling this method several times fails the next requests in order.
+    pub fn fail_next(&self, status: u16, body: Value) {
+        self.state
+            .failures
+            .lock()
+            .unwrap()
+            .push_back((status, body));
+    }
+
+    /// Forgets the recorded requests and the queued failures.
+    pub fn clear(&self) {
+        self.state.requests.lock().unwrap().clear();
+        self.state.failures.lock().unwrap().clear();
+    }
+}
+
+impl Drop for MockGraph {
+    fn drop(&mut self) {
+        drop(self.handle.stop(false));
+    }
+}
+
+#[derive(Deserialize)]
+struct GraphQuery {
+    access_token: Option<String>,
+}
+
+fn failure(status: u16, body: Value) -> HttpResponse {
+    HttpResponse::build(
+        actix_web::http::StatusCode::from_u16(status)
+            .unwrap_or(actix_web::http::StatusCode::INTERNAL_SERVER_ERROR),
+    )
+    .json(body)
+}
+
+#[get("/{version}/me")]
+async fn page(state: web::Data<State>) -> HttpResponse {
+    match state.failures.lock().unwrap().pop_front() {
+        Some((status, body)) => failure(status, body),
+        None => HttpResponse::Ok().json(json!({ "id": "mock_page_id", "name": "Mock Page" })),
+    }
+}
+
+#[post("/{version}/me/{endpoint}")]
+async fn graph(
+    path: web::Path<(String, String)>,
+    query: web::Query<GraphQuery>,
+    body: web::Json<Value>,
+    state: web::Data<State>,
+) -> HttpResponse {
+    let (version, endpoint) = path.into_inner();
+    let request = RecordedRequest {
+        version,
+        endpoint,
+        access_token: query.into_inner().access_token,
+        body: body.into_inner(),
+    };
+
+    let response = match state.failures.lock().unwrap().pop_front() {
+        Some((status, body)) => failure(status, body),
+        None if request.endpoint == "messages" => {
+            let count = state.requests.lock().unwrap().len();
+            HttpResponse::Ok().json(json!({
+                "recipient_id": request.recipient(),
+                "message_id": format!("m_{count}"),
+            }))
+        }
+        None => HttpResponse::Ok().json(json!({ "result": "success" })),
+    };
+
+    state.requests.lock().unwrap().push(request);
+    response
+}
@@ -0,0 +1,15 @@
+//! The `testing` module provides utilities to test a bot without a real Facebook page.
+//!
+//! This module is only available with the `testing` feature.
+//!
+//! # Submodules
+//!
+//! * `events`: This module builds synthetic webhook events.
+//! * `mock_graph`: This module contains `MockGraph`, an in-process stand-in for the Graph API.
+//! * `test_app`: This module contains `TestApp`, which drives the routes of an application with synthetic events.
+pub mod events;
+pub mod mock_graph;
+pub mod test_app;
+
+pub use mock_graph::{MockGraph, RecordedRequest};
+pub use test_app::TestApp;
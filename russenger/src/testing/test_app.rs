@@ -0,0 +1,298 @@
+//! The `test_app` module contains `TestApp`, which runs an application against an in-memory database and a `MockGraph`.
+//!
+//! `TestApp` posts synthetic webhook events to the real `/webhook` endpoint, waits until every queued action has run,
+//! and then lets the test inspect what was sent to each user and on which path each user ended up.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use russenger::prelude::*;
+//! use russenger::testing::TestApp;
+//!
+//! async fn index(res: Res, req: Req) -> Result<()> {
+//!     res.send(TextModel::new(&req.user, "What is your name?")).await?;
+//!     res.redirect("/name").await?;
+//!     Ok(())
+//! }
+//!
+//! async fn name(res: Res, req: Req) -> Result<()> {
+//!     let name: String = req.data.get_value()?;
+//!     res.send(TextModel::new(&req.user, format!("Hello {name}"))).await?;
+//!     res.redirect("/").await?;
+//!     Ok(())
+//! }
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<()> {
+//!     let app = TestApp::new(router![("/", index), ("/name", name)]).await?;
+//!
+//!     app.text("user_id", "Hi").await?;
+//!     app.assert_path("user_id", "/name").await;
+//!
+//!     app.text("user_id", "Joe").await?;
+//!     app.assert_sent("user_id", TextModel::new("user_id", "Hello Joe"));
+//!     assert_eq!(app.texts_to("user_id"), ["What is your name?", "Hello Joe"]);
+//!     Ok(())
+//! }
+//! ```
+use std::{
+    io,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{Duration, Instant},
+};
+
+use actix_web::{dev::ServerHandle, App as ActixApp, HttpServer};
+use serde_json::Value;
+
+use crate::core::graph::{GraphClient, RetryPolicy};
+use crate::error::Result;
+use crate::response_models::{payload::Payload, ResponseModel};
+use crate::services::{
+    handlers::page_context,
+    signature::{sign, SIGNATURE_HEADER},
+    worker::WorkerPool,
+    InComingData,
+};
+use crate::{App, Database, Router};
+
+use super::{events, MockGraph};
+
+/// How long `TestApp` waits for the queued actions to run after posting an event.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(10);
+
+static DATABASE_SEQ: AtomicUsize = AtomicUsize::new(0);
+
+fn memory_database_url() -> String {
+    let seq = DATABASE_SEQ.fetch_add(1, Ordering::Relaxed);
+    format!(
+        "sqlite:file:russenger-test-{pid}-{seq}?mode=memory&cache=shared",
+        pid = std::process::id()
+    )
+}
+
+/// `TestApp` runs an application on a local port, with an in-memory database and a `MockGraph`.
+pub struct TestApp {
+    app: App,
+    graph: MockGraph,
+    url: String,
+    http: reqwest::Client,
+    handle: ServerHandle,
+    workers: Option<WorkerPool>,
+}
+
+impl TestApp {
+    /// Starts an application with the routes of `router`.
+    pub async fn new(router: Router) -> Result<Self> {
+        Self::build(|app| app.attach(router)).await
+    }
+
+    /// Starts an application configured by `configure`, to attach routers, middlewares or an error handler.
+    ///
+    /// The webhook signature is not verified unless `configure` sets an App Secret, in which case the events posted by
+    /// `TestApp` are signed with it.
+    pub async fn build(configure: impl FnOnce(App) -> App) -> Result<Self> {
+        let graph = MockGraph::start().await?;
+        let database = Database::new(&memory_database_url()).await?;
+        let graph_client = GraphClient::new("v19.0", "test_page_access_token")
+            .base_url(graph.url())
+            .retry_policy(RetryPolicy::none());
+
+        let app = App::from_database(database, graph_client)
+            .await?
+            .disable_signature_verification();
+        let app = configure(app).disable_static_files();
+        let (app, workers) = app.start_workers();
+
+        let served = app.clone();
+        let server =
+            HttpServer::new(move || ActixApp::new().configure(|cfg| served.configure(cfg)))
+                .workers(1)
+                .bind(("127.0.0.1", 0))?;
+        let addr = server.addrs()[0];
+        let server = server.run();
+        let handle = server.handle();
+        tokio::spawn(server);
+
+        Ok(Self {
+            app,
+            graph,
+            url: format!("http://{addr}/webhook"),
+            http: reqwest::Client::new(),
+            handle,
+            workers: Some(workers),
+        })
+    }
+
+    /// Posts `data` to the webhook and waits until every queued action has run.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the webhook does not answer with a success, or if the actions do not finish in time.
+    pub async fn send(&self, data: InComingData) -> Result<()> {
+        let body = serde_json::to_vec(&data)?;
+        let mut request = self
+            .http
+            .post(&self.url)
+            .header("Content-Type", "application/json");
+        if let Some(app_secret) = &self.app.app_secret {
+            request = request.header(SIGNATURE_HEADER, sign(app_secret, &body));
+        }
+
+        let response = request.body(body).send().await?;
+        if !response.status().is_success() {
+            let message = format!("Webhook answered {}", response.status());
+            return Err(io::Error::other(message).into());
+        }
+        self.wait_idle().await
+    }
+
+    /// Sends a text message from `user`.
+    pub async fn text(&self, user: &str, text: &str) -> Result<()> {
+        self.send(events::text(user, text)).await
+    }
+
+    /// Sends a quick reply with the title `text` from `user`.
+    ///
+    /// The payload is signed when the app signs its payloads, as if it was sent by the app.
+    pub async fn quick_reply(&self, user: &str, text: &str, payload: Payload) -> Result<()> {
+        let payload = self.sign(payload);
+        self.send(events::quick_reply(user, text, payload)).await
+    }
+
+    /// Sends a postback from `user`.
+    ///
+    /// The payload is signed when the app signs its payloads, as if it was sent by the app.
+    pub async fn postback(&self, user: &str, payload: Payload) -> Result<()> {
+        let payload = self.sign(payload);
+        self.send(events::postback(user, payload)).await
+    }
+
+    fn sign(&self, payload: Payload) -> Payload {
+        match &self.app.payload_secret {
+            Some(secret) => payload.sign(secret),
+            None => payload,
+        }
+    }
+
+    async fn wait_idle(&self) -> Result<()> {
+        let start = Instant::now();
+        while !self.app.user_queues.is_idle().await {
+            if start.elapsed() > IDLE_TIMEOUT {
+                let message = "Actions did not finish in time";
+                return Err(io::Error::new(io::ErrorKind::TimedOut, message).into());
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        Ok(())
+    }
+
+    /// Stops the workers of the application, once the running and the queued actions have finished.
+    ///
+    /// The webhook then answers `503 Service Unavailable`, as an application whose workers were shut down.
+    pub async fn shutdown(&mut self) {
+        if let Some(workers) = self.workers.take() {
+            workers.shutdown().await;
+        }
+    }
+
+    /// The URL of the `/webhook` endpoint of the application, to post events that `TestApp` does not build.
+    pub fn webhook_url(&self) -> &str {
+        &self.url
+    }
+
+    /// The `MockGraph` receiving the responses of the application.
+    pub fn graph(&self) -> &MockGraph {
+        &self.graph
+    }
+
+    /// Returns the bodies of the responses sent to `user`, in order.
+    pub fn sent_to(&self, user: &str) -> Vec<Value> {
+        self.graph
+            .requests_to(user)
+            .into_iter()
+            .map(|request| request.body)
+            .collect()
+    }
+
+    /// Returns the texts of the messages sent to `user`, in order, including the text of quick replies.
+    pub fn texts_to(&self, user: &str) -> Vec<String> {
+        self.sent_to(user)
+            .iter()
+            .filter_map(|body| body["message"]["text"].as_str().map(str::to_owned))
+            .collect()
+    }
+
+    /// Returns the path on which `user` of the default page currently is.
+    pub async fn action_path(&self, user: &str) -> Option<String> {
+        self.action_path_on("", user).await
+    }
+
+    /// Returns the path on which `user` of the page `page_id` currently is.
+    ///
+    /// The users of a page registered with `App::page` have their own path on it, see `events::on_page`.
+    pub async fn action_path_on(&self, page_id: &str, user: &str) -> Option<String> {
+        let (query, _) = page_context(&self.app, page_id);
+        query.get_path(user).await.ok().flatten()
+    }
+
+    /// Asserts that `response_model` was sent to `user`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no response sent to `user` is equal to `response_model`.
+    pub fn assert_sent(&self, user: &str, response_model: impl ResponseModel) {
+        let expected = serde_json::to_value(&response_model).expect("Invalid response model");
+        let sent = self.sent_to(user);
+        assert!(
+            sent.contains(&expected),
+            "{expected} was not sent to {user}, sent: {sent:#?}"
+        );
+    }
+
+    /// Asserts that a message with `text` was sent to `user`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no message with `text` was sent to `user`.
+    pub fn assert_text(&self, user: &str, text: &str) {
+        let texts = self.texts_to(user);
+        assert!(
+            texts.iter().any(|sent| sent == text),
+            "{text:?} was not sent to {user}, sent: {texts:?}"
+        );
+    }
+
+    /// Asserts that `user` of the default page is on `path`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `user` is on another path, or is unknown.
+    pub async fn assert_path(&self, user: &str, path: &str) {
+        self.assert_path_on("", user, path).await;
+    }
+
+    /// Asserts that `user` of the page `page_id` is on `path`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `user` is on another path of the page, or is unknown to the page.
+    pub async fn assert_path_on(&self, page_id: &str, user: &str, path: &str) {
+        let action_path = self.action_path_on(page_id, user).await;
+        assert_eq!(
+            action_path.as_deref(),
+            Some(path),
+            "{user} is not on {path}"
+        );
+    }
+
+    /// Forgets the responses recorded so far.
+    pub fn clear(&self) {
+        self.graph.clear();
+    }
+}
+
+impl Drop for TestApp {
+    fn drop(&mut self) {
+        drop(self.handle.stop(false));
+    }
+}
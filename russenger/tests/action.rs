@@ -0,0 +1,151 @@
+//! The arguments of an `#[action]` are extracted before its body runs, and the extraction errors reach `on_error`.
+#![cfg(feature = "testing")]
+
+use std::io;
+
+use russenger::core::extract::FromRequest;
+use russenger::prelude::*;
+use russenger::testing::TestApp;
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize)]
+struct Order {
+    product: String,
+    quantity: u32,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct Cart {
+    products: Vec<String>,
+}
+
+impl SessionValue for Cart {
+    const KEY: &'static str = "cart";
+}
+
+/// An extractor that always fails, as a database lookup could.
+struct Admin;
+
+impl FromRequest for Admin {
+    async fn from_request(_: &Res, _: &Req) -> Result<Self> {
+        Err(io::Error::other("not an admin").into())
+    }
+}
+
+#[action]
+async fn echo(Text(text): Text, req: Req, res: Res) -> Result<()> {
+    res.send(TextModel::new(&req.user, format!("Echo {text}")))
+        .await?;
+    Ok(())
+}
+
+#[action]
+async fn order(res: Res, Json(order): Json<Order>, req: Req) -> Result<()> {
+    let message = format!("{} x {}", order.quantity, order.product);
+    res.send(TextModel::new(&req.user, message)).await?;
+    Ok(())
+}
+
+#[action]
+async fn admin(res: Res, req: Req, _: Admin) -> Result<()> {
+    res.send(TextModel::new(&req.user, "Admin")).await?;
+    Ok(())
+}
+
+#[action]
+async fn add_to_cart(
+    res: Res,
+    req: Req,
+    Text(product): Text,
+    mut cart: Session<Cart>,
+) -> Result<()> {
+    cart.products.push(product);
+    cart.save().await?;
+    res.send(TextModel::new(&req.user, cart.products.join(", ")))
+        .await?;
+    Ok(())
+}
+
+async fn on_error(res: Res, req: Req, path: String, err: error::Error) -> Result<()> {
+    let kind = if err.is::<serde_json::Error>() {
+        "invalid data"
+    } else {
+        "other"
+    };
+    res.send(TextModel::new(
+        &req.user,
+        format!("{path} failed: {kind}, {err}"),
+    ))
+    .await?;
+    Ok(())
+}
+
+async fn app() -> Result<TestApp> {
+    TestApp::build(|app| {
+        app.on_error(on_error).attach(router![
+            ("/", echo),
+            ("/order", order),
+            ("/admin", admin),
+            ("/cart", add_to_cart)
+        ])
+    })
+    .await
+}
+
+#[tokio::test]
+async fn arguments_are_extracted_in_any_order() -> Result<()> {
+    let app = app().await?;
+
+    app.text("user_id", "Hi").await?;
+    let data = Data::new(serde_json::json!({ "product": "tea", "quantity": 2 }));
+    app.postback("user_id", Payload::new("/order", Some(data)))
+        .await?;
+
+    assert_eq!(app.texts_to("user_id"), ["Echo Hi", "2 x tea"]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn json_arguments_reject_data_of_another_shape() -> Result<()> {
+    let app = app().await?;
+
+    let data = Data::new("tea");
+    app.postback("user_id", Payload::new("/order", Some(data)))
+        .await?;
+
+    let texts = app.texts_to("user_id");
+    assert_eq!(texts.len(), 1);
+    assert!(
+        texts[0].starts_with("/order failed: invalid data"),
+        "{}",
+        texts[0]
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn extraction_failures_reach_the_error_handler() -> Result<()> {
+    let app = app().await?;
+
+    app.postback("user_id", Payload::new("/admin", None))
+        .await?;
+
+    assert_eq!(
+        app.texts_to("user_id"),
+        ["/admin failed: other, not an admin"]
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn typed_sessions_default_and_are_saved() -> Result<()> {
+    let app = app().await?;
+
+    app.postback("user_id", Payload::new("/cart", Some(Data::new("tea"))))
+        .await?;
+    app.postback("user_id", Payload::new("/cart", Some(Data::new("milk"))))
+        .await?;
+
+    assert_eq!(app.texts_to("user_id"), ["tea", "tea, milk"]);
+    Ok(())
+}
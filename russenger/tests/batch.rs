@@ -0,0 +1,37 @@
+//! The events batched by Facebook in a single webhook call are grouped by sender, and each sender keeps their order.
+#![cfg(feature = "testing")]
+
+use std::time::Duration;
+
+use russenger::prelude::*;
+use russenger::testing::{events, TestApp};
+
+/// Replies with the text, the earlier events being the slowest.
+async fn echo(res: Res, req: Req) -> Result<()> {
+    let text: String = req.data.get_value()?;
+    let delay = 200 - 50 * text.parse::<u64>()?;
+    tokio::time::sleep(Duration::from_millis(delay)).await;
+    res.send(TextModel::new(&req.user, text)).await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn interleaved_senders_are_handled_in_order() -> Result<()> {
+    let app = TestApp::new(router![("/", echo)]).await?;
+
+    app.send(events::batch([
+        events::text("alice", "1"),
+        events::text("bob", "1"),
+        events::text("alice", "2"),
+        events::text("carol", "1"),
+        events::text("bob", "2"),
+        events::text("alice", "3"),
+    ]))
+    .await?;
+
+    assert_eq!(app.texts_to("alice"), ["1", "2", "3"]);
+    assert_eq!(app.texts_to("bob"), ["1", "2"]);
+    assert_eq!(app.texts_to("carol"), ["1"]);
+    assert_eq!(app.graph().requests().len(), 6);
+    Ok(())
+}
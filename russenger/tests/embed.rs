@@ -0,0 +1,100 @@
+//! An `App` mounted in an existing actix-web application with `App::scope` serves its webhook under the scope.
+#![cfg(feature = "testing")]
+
+use std::time::{Duration, Instant};
+
+use actix_web::{test, App as ActixApp};
+use russenger::prelude::*;
+use russenger::testing::{events, MockGraph};
+
+async fn index(res: Res, req: Req) -> Result<()> {
+    res.send(TextModel::new(&req.user, "Hello")).await?;
+    Ok(())
+}
+
+async fn app(graph: &MockGraph) -> Result<App> {
+    let config = AppConfig::new()
+        .page_access_token("page_access_token")
+        .verify_token("verify_token")
+        .database_url("sqlite::memory:")
+        .graph_api_url(graph.url());
+    Ok(App::with_config(config)
+        .await?
+        .disable_static_files()
+        .attach(router![("/", index)]))
+}
+
+#[actix_web::test]
+async fn the_webhook_is_verified_under_the_scope() -> Result<()> {
+    let graph = MockGraph::start().await?;
+    let (app, workers) = app(&graph).await?.start_workers();
+    let service = test::init_service(ActixApp::new().service(app.scope("/messenger"))).await;
+
+    let uri = "/messenger/webhook?hub.mode=subscribe&hub.challenge=challenge&hub.verify_token=verify_token";
+    let response =
+        test::call_service(&service, test::TestRequest::get().uri(uri).to_request()).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(test::read_body(response).await, "challenge");
+
+    let uri =
+        "/messenger/webhook?hub.mode=subscribe&hub.challenge=challenge&hub.verify_token=wrong";
+    let response =
+        test::call_service(&service, test::TestRequest::get().uri(uri).to_request()).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = test::call_service(
+        &service,
+        test::TestRequest::get().uri("/webhook").to_request(),
+    )
+    .await;
+    assert_eq!(response.status().as_u16(), 404);
+
+    workers.shutdown().await;
+    Ok(())
+}
+
+#[actix_web::test]
+async fn events_posted_under_the_scope_reach_the_actions() -> Result<()> {
+    let graph = MockGraph::start().await?;
+    let (app, workers) = app(&graph)
+        .await?
+        .disable_signature_verification()
+        .start_workers();
+    let service = test::init_service(ActixApp::new().service(app.scope("/messenger"))).await;
+
+    let request = test::TestRequest::post()
+        .uri("/messenger/webhook")
+        .set_json(events::text("user_id", "Hi"))
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let start = Instant::now();
+    while graph.requests_to("user_id").is_empty() && start.elapsed() < Duration::from_secs(10) {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    let requests = graph.requests_to("user_id");
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].body["message"]["text"], "Hello");
+
+    workers.shutdown().await;
+    Ok(())
+}
+
+#[actix_web::test]
+async fn events_are_refused_without_an_app_secret() -> Result<()> {
+    let graph = MockGraph::start().await?;
+    let (app, workers) = app(&graph).await?.start_workers();
+    let service = test::init_service(ActixApp::new().service(app.scope("/messenger"))).await;
+
+    let request = test::TestRequest::post()
+        .uri("/messenger/webhook")
+        .set_json(events::text("user_id", "Hi"))
+        .to_request();
+    let response = test::call_service(&service, request).await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    workers.shutdown().await;
+    assert!(graph.requests().is_empty());
+    Ok(())
+}
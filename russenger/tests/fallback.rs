@@ -0,0 +1,93 @@
+//! The fallback runs when the path stored for a user has no route, and `reset_unknown_path` sends the user back to `/`.
+#![cfg(feature = "testing")]
+
+use russenger::prelude::*;
+use russenger::testing::TestApp;
+
+async fn index(res: Res, req: Req) -> Result<()> {
+    res.send(TextModel::new(&req.user, "Hello")).await?;
+    res.redirect("/removed").await?;
+    Ok(())
+}
+
+async fn not_found(res: Res, req: Req) -> Result<()> {
+    res.send(TextModel::new(&req.user, format!("{} not found", req.path)))
+        .await?;
+    Ok(())
+}
+
+async fn on_error(res: Res, req: Req, path: String, _: error::Error) -> Result<()> {
+    res.send(TextModel::new(&req.user, format!("{path} failed")))
+        .await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn unknown_paths_without_a_fallback_reach_the_error_handler() -> Result<()> {
+    let app = TestApp::build(|app| app.on_error(on_error).attach(router![("/", index)])).await?;
+
+    app.text("user_id", "Hi").await?;
+    app.text("user_id", "Hi again").await?;
+
+    assert_eq!(app.texts_to("user_id"), ["Hello", "/removed failed"]);
+    app.assert_path("user_id", "/removed").await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn unknown_paths_run_the_fallback() -> Result<()> {
+    let app = TestApp::new(router![("/", index)].fallback(not_found)).await?;
+
+    app.text("user_id", "Hi").await?;
+    app.text("user_id", "Hi again").await?;
+
+    assert_eq!(app.texts_to("user_id"), ["Hello", "/removed not found"]);
+    app.assert_path("user_id", "/removed").await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn unknown_paths_are_reset_before_the_fallback_runs() -> Result<()> {
+    let router = router![("/", index)]
+        .fallback(not_found)
+        .reset_unknown_path();
+    let app = TestApp::new(router).await?;
+
+    app.text("user_id", "Hi").await?;
+    app.text("user_id", "Hi again").await?;
+    app.assert_path("user_id", "/").await;
+    app.text("user_id", "Hi once more").await?;
+
+    assert_eq!(
+        app.texts_to("user_id"),
+        ["Hello", "/removed not found", "Hello"]
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn the_fallback_is_kept_with_the_router_that_set_it() -> Result<()> {
+    let app = TestApp::build(|app| {
+        app.attach(router![("/", index)])
+            .attach(Router::new().fallback(not_found).reset_unknown_path())
+            .attach(router![("/help", index)])
+    })
+    .await?;
+
+    app.text("user_id", "Hi").await?;
+    app.text("user_id", "Hi again").await?;
+
+    assert_eq!(app.texts_to("user_id"), ["Hello", "/removed not found"]);
+    app.assert_path("user_id", "/").await;
+    Ok(())
+}
+
+#[tokio::test]
+#[should_panic(expected = "Two attached routers set a fallback")]
+async fn two_routers_can_not_set_a_fallback() {
+    let _ = TestApp::build(|app| {
+        app.attach(router![("/", index)].fallback(not_found))
+            .attach(Router::new().reset_unknown_path())
+    })
+    .await;
+}
@@ -0,0 +1,124 @@
+//! `/healthz` answers as long as the server runs, and `/readyz` reports the workers, the database and the page tokens.
+#![cfg(feature = "testing")]
+
+use actix_web::{test, App as ActixApp};
+use russenger::prelude::*;
+use russenger::testing::{MockGraph, TestApp};
+use serde_json::{json, Value};
+
+async fn index(res: Res, req: Req) -> Result<()> {
+    res.send(TextModel::new(&req.user, "Hello")).await?;
+    Ok(())
+}
+
+async fn get(app: &TestApp, path: &str) -> Result<(u16, String)> {
+    let url = app.webhook_url().replace("/webhook", path);
+    let response = reqwest::get(url).await?;
+    Ok((response.status().as_u16(), response.text().await?))
+}
+
+async fn readyz(app: &TestApp) -> Result<(u16, Value)> {
+    let (status, body) = get(app, "/readyz").await?;
+    Ok((status, serde_json::from_str(&body)?))
+}
+
+#[tokio::test]
+async fn a_running_app_is_healthy_and_ready() -> Result<()> {
+    let app = TestApp::new(router![("/", index)]).await?;
+
+    assert_eq!(get(&app, "/healthz").await?, (200, "OK".to_owned()));
+    assert_eq!(
+        readyz(&app).await?,
+        (
+            200,
+            json!({ "workers": "ok", "database": "ok", "page_token": "ok" })
+        )
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn an_invalid_page_token_is_not_ready() -> Result<()> {
+    let app = TestApp::new(router![("/", index)]).await?;
+    app.graph().fail_next(
+        400,
+        json!({ "error": { "message": "Invalid OAuth access token", "code": 190 } }),
+    );
+
+    let (status, body) = readyz(&app).await?;
+    assert_eq!(status, 503);
+    assert_eq!(body["workers"], "ok");
+    assert!(
+        body["page_token"]
+            .as_str()
+            .unwrap()
+            .contains("Invalid OAuth access token"),
+        "{body}"
+    );
+
+    assert_eq!(readyz(&app).await?.0, 200);
+    Ok(())
+}
+
+#[tokio::test]
+async fn the_tokens_of_every_page_are_checked() -> Result<()> {
+    let app = TestApp::build(|app| {
+        app.page("page_1", "token_1")
+            .page("page_2", "token_2")
+            .attach(router![("/", index)])
+    })
+    .await?;
+    // One failure for the default page and for each registered page: the next probe is only ready if every
+    // token was checked, instead of stopping at the first failure.
+    for _ in 0..3 {
+        app.graph().fail_next(
+            400,
+            json!({ "error": { "message": "Invalid OAuth access token", "code": 190 } }),
+        );
+    }
+
+    let (status, body) = readyz(&app).await?;
+    assert_eq!(status, 503);
+    assert_eq!(
+        body["page_token"],
+        "invalid access token: Invalid OAuth access token"
+    );
+
+    assert_eq!(readyz(&app).await?.0, 200);
+    Ok(())
+}
+
+#[tokio::test]
+async fn a_shutting_down_app_is_not_ready() -> Result<()> {
+    let mut app = TestApp::new(router![("/", index)]).await?;
+    app.shutdown().await;
+
+    let (status, body) = readyz(&app).await?;
+    assert_eq!(status, 503);
+    assert_eq!(body["workers"], "Shutting down");
+    assert_eq!(get(&app, "/healthz").await?.0, 200);
+    Ok(())
+}
+
+#[actix_web::test]
+async fn an_app_without_workers_is_not_ready() -> Result<()> {
+    let graph = MockGraph::start().await?;
+    let config = AppConfig::new()
+        .page_access_token("page_access_token")
+        .verify_token("verify_token")
+        .database_url("sqlite::memory:")
+        .graph_api_url(graph.url());
+    let app = App::with_config(config).await?.disable_static_files();
+    let service = test::init_service(ActixApp::new().configure(|cfg| app.configure(cfg))).await;
+
+    let request = test::TestRequest::get().uri("/readyz").to_request();
+    let response = test::call_service(&service, request).await;
+
+    assert_eq!(response.status().as_u16(), 503);
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(
+        body,
+        json!({ "workers": "Workers are not running", "database": "ok", "page_token": "ok" })
+    );
+    Ok(())
+}
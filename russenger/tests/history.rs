@@ -0,0 +1,92 @@
+//! `res.back()` and the back quick reply return the user to the previous step of their history.
+#![cfg(feature = "testing")]
+
+use russenger::prelude::*;
+use russenger::response_models::next::BACK_PATH;
+use russenger::testing::TestApp;
+
+async fn index(res: Res, req: Req) -> Result<()> {
+    res.send(TextModel::new(&req.user, "Home")).await?;
+    Ok(())
+}
+
+async fn product(res: Res, req: Req) -> Result<()> {
+    let id: u32 = req.data.get_value()?;
+    res.send(TextModel::new(&req.user, format!("Product {id}")))
+        .await?;
+    Ok(())
+}
+
+async fn cart(res: Res, req: Req) -> Result<()> {
+    res.send(TextModel::new(&req.user, "Cart")).await?;
+    Ok(())
+}
+
+async fn back(res: Res, _: Req) -> Result<()> {
+    res.back().await
+}
+
+async fn app() -> Result<TestApp> {
+    TestApp::new(router![
+        ("/", index),
+        ("/product", product),
+        ("/cart", cart),
+        ("/back", back)
+    ])
+    .await
+}
+
+fn back_payload() -> Payload {
+    Payload::new(BACK_PATH, None)
+}
+
+#[tokio::test]
+async fn the_back_quick_reply_runs_the_previous_step_again() -> Result<()> {
+    let app = app().await?;
+
+    app.postback("user_id", Payload::new("/", None)).await?;
+    app.postback("user_id", Payload::new("/product", Some(Data::new(1))))
+        .await?;
+    app.postback("user_id", Payload::new("/product", Some(Data::new(2))))
+        .await?;
+    app.postback("user_id", Payload::new("/cart", None)).await?;
+    app.clear();
+
+    app.postback("user_id", back_payload()).await?;
+    app.postback("user_id", back_payload()).await?;
+    app.postback("user_id", back_payload()).await?;
+
+    assert_eq!(app.texts_to("user_id"), ["Product 2", "Home", "Home"]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn res_back_redirects_to_the_previous_path() -> Result<()> {
+    let app = app().await?;
+
+    app.postback("user_id", Payload::new("/cart", None)).await?;
+    app.postback("user_id", Payload::new("/back", None)).await?;
+
+    app.assert_path("user_id", "/cart").await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn only_the_last_steps_are_kept() -> Result<()> {
+    let app = app().await?;
+
+    for id in 0..30 {
+        app.postback("user_id", Payload::new("/product", Some(Data::new(id))))
+            .await?;
+        app.postback("user_id", Payload::new("/cart", None)).await?;
+    }
+    app.clear();
+    for _ in 0..25 {
+        app.postback("user_id", back_payload()).await?;
+    }
+
+    let texts = app.texts_to("user_id");
+    assert_eq!(texts[..2], ["Product 29", "Cart"]);
+    assert_eq!(texts.last().map(String::as_str), Some("Home"));
+    Ok(())
+}
@@ -0,0 +1,67 @@
+//! The events, the actions and the Graph API requests are counted in the series served at `/metrics`.
+#![cfg(all(feature = "testing", feature = "metrics"))]
+
+use russenger::prelude::*;
+use russenger::testing::TestApp;
+
+async fn greet(res: Res, req: Req) -> Result<()> {
+    res.send(TextModel::new(&req.user, "Hello")).await?;
+    res.redirect("/metrics_fail").await?;
+    Ok(())
+}
+
+async fn fail(_: Res, req: Req) -> Result<()> {
+    let _: u32 = req.data.get_value()?;
+    Ok(())
+}
+
+/// Returns the value of `series`, like `name{label="value"}`, in the Prometheus text format.
+fn value(metrics: &str, series: &str) -> Option<f64> {
+    metrics
+        .lines()
+        .find_map(|line| line.strip_prefix(series)?.strip_prefix(' ')?.parse().ok())
+}
+
+#[tokio::test]
+async fn events_actions_and_graph_requests_are_recorded() -> Result<()> {
+    let app = TestApp::new(router![("/", greet), ("/metrics_fail", fail)]).await?;
+
+    app.text("user_id", "Hi").await?;
+    app.text("user_id", "Not a number").await?;
+
+    let url = app.webhook_url().replace("/webhook", "/metrics");
+    let response = reqwest::get(url).await?;
+    assert_eq!(response.status().as_u16(), 200);
+    let metrics = response.text().await?;
+
+    let series = [
+        (r#"russenger_events_received_total{type="text"}"#, 2.0),
+        (r#"russenger_action_duration_seconds_count{route="/"}"#, 1.0),
+        (
+            r#"russenger_action_duration_seconds_count{route="/metrics_fail"}"#,
+            1.0,
+        ),
+        (
+            r#"russenger_action_errors_total{route="/metrics_fail"}"#,
+            1.0,
+        ),
+        (
+            r#"russenger_graph_request_duration_seconds_count{endpoint="messages",status="success"}"#,
+            1.0,
+        ),
+    ];
+    for (series, expected) in series {
+        assert_eq!(
+            value(&metrics, series),
+            Some(expected),
+            "{series} in {metrics}"
+        );
+    }
+    assert_eq!(
+        value(&metrics, r#"russenger_action_errors_total{route="/"}"#),
+        None
+    );
+    assert!(metrics.contains("russenger_db_query_duration_seconds_count{operation="));
+    assert!(metrics.contains("# TYPE russenger_queue_dropped_events_total counter"));
+    Ok(())
+}
@@ -0,0 +1,119 @@
+//! Middlewares wrap the actions in the order they were added, global ones first, and can skip the action.
+#![cfg(feature = "testing")]
+
+use russenger::core::router::FutureResult;
+use russenger::prelude::*;
+use russenger::testing::TestApp;
+
+/// Sends `before {name}` and `after {name}` around the rest of the chain.
+struct Tag(&'static str);
+
+impl Middleware for Tag {
+    fn call(&self, res: Res, req: Req, next: Next) -> FutureResult {
+        let name = self.0;
+        Box::pin(async move {
+            let user = req.user.clone();
+            res.send(TextModel::new(&user, format!("before {name}")))
+                .await?;
+            next.run(res.clone(), req).await?;
+            res.send(TextModel::new(&user, format!("after {name}")))
+                .await?;
+            Ok(())
+        })
+    }
+}
+
+async fn index(res: Res, req: Req) -> Result<()> {
+    res.send(TextModel::new(&req.user, "index")).await?;
+    res.redirect("/next").await?;
+    Ok(())
+}
+
+async fn blocked(res: Res, req: Req, _: Next) -> Result<()> {
+    res.send(TextModel::new(&req.user, "blocked")).await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn middlewares_run_in_the_order_they_were_added() -> Result<()> {
+    let router = Router::new()
+        .middleware(Tag("first"))
+        .middleware(Tag("second"))
+        .add("/", index);
+    let app = TestApp::new(router).await?;
+
+    app.text("user_id", "Hi").await?;
+
+    assert_eq!(
+        app.texts_to("user_id"),
+        [
+            "before first",
+            "before second",
+            "index",
+            "after second",
+            "after first"
+        ]
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn a_middleware_not_calling_next_skips_the_action() -> Result<()> {
+    let router = Router::new()
+        .middleware(Tag("outer"))
+        .middleware(blocked)
+        .add("/", index);
+    let app = TestApp::new(router).await?;
+
+    app.text("user_id", "Hi").await?;
+
+    assert_eq!(
+        app.texts_to("user_id"),
+        ["before outer", "blocked", "after outer"]
+    );
+    app.assert_path("user_id", "/").await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn global_middlewares_wrap_the_middlewares_of_every_router() -> Result<()> {
+    let app = TestApp::build(|app| {
+        app.attach(Router::new().middleware(Tag("router")).add("/", index))
+            .attach(Router::new().add("/next", index))
+            .middleware(Tag("global"))
+    })
+    .await?;
+
+    app.text("user_id", "Hi").await?;
+    app.clear();
+    app.text("user_id", "Hi again").await?;
+
+    assert_eq!(
+        app.texts_to("user_id"),
+        ["before global", "index", "after global"]
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn global_middlewares_run_before_the_router_ones() -> Result<()> {
+    let app = TestApp::build(|app| {
+        app.middleware(Tag("global"))
+            .attach(Router::new().middleware(Tag("router")).add("/", index))
+    })
+    .await?;
+
+    app.text("user_id", "Hi").await?;
+
+    assert_eq!(
+        app.texts_to("user_id"),
+        [
+            "before global",
+            "before router",
+            "index",
+            "after router",
+            "after global"
+        ]
+    );
+    Ok(())
+}
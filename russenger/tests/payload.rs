@@ -0,0 +1,89 @@
+//! The `Data` of the payloads too long for Facebook is stored in the database and loaded back.
+#![cfg(feature = "testing")]
+
+use std::str::FromStr;
+
+use russenger::db::MAX_PAYLOAD_LENGTH;
+use russenger::prelude::*;
+use russenger::testing::{events, TestApp};
+
+fn long_text() -> String {
+    "a".repeat(2 * MAX_PAYLOAD_LENGTH)
+}
+
+async fn index(res: Res, req: Req) -> Result<()> {
+    let payload = Payload::new("/echo", Some(Data::new(long_text())));
+    let quick_replies = vec![QuickReply::new("Echo", None, payload)];
+    res.send(QuickReplyModel::new(&req.user, "Echo?", quick_replies))
+        .await?;
+    Ok(())
+}
+
+async fn echo(res: Res, req: Req) -> Result<()> {
+    let text: String = req.data.get_value()?;
+    res.send(TextModel::new(
+        &req.user,
+        format!("{} characters", text.len()),
+    ))
+    .await?;
+    Ok(())
+}
+
+async fn on_rejected(res: Res, req: Req, path: String, err: error::Error) -> Result<()> {
+    let expired = matches!(
+        err.downcast_ref::<PayloadError>(),
+        Some(PayloadError::Expired)
+    );
+    res.send(TextModel::new(
+        &req.user,
+        format!("{path} rejected, expired: {expired}"),
+    ))
+    .await?;
+    Ok(())
+}
+
+async fn app() -> Result<TestApp> {
+    TestApp::build(|app| {
+        app.on_rejected_payload(on_rejected)
+            .attach(router![("/", index), ("/echo", echo)])
+    })
+    .await
+}
+
+/// Returns the payload of the first quick reply sent to `user`.
+fn sent_payload(app: &TestApp, user: &str) -> Payload {
+    let sent = app.sent_to(user);
+    let payload = sent[0]["message"]["quick_replies"][0]["payload"]
+        .as_str()
+        .expect("No quick reply sent");
+    assert!(payload.len() <= MAX_PAYLOAD_LENGTH, "{payload}");
+    Payload::from_str(payload).expect("Invalid payload")
+}
+
+#[tokio::test]
+async fn long_payloads_are_loaded_back() -> Result<()> {
+    let app = app().await?;
+
+    app.text("user_id", "Hi").await?;
+    let payload = sent_payload(&app, "user_id");
+    app.send(events::quick_reply("user_id", "Echo", payload))
+        .await?;
+
+    assert_eq!(
+        app.texts_to("user_id"),
+        ["Echo?", &format!("{} characters", long_text().len())]
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn payloads_whose_data_is_gone_are_rejected() -> Result<()> {
+    let app = app().await?;
+    let payload = r#"{"action_path":"/echo","data":null,"key":"00000000000000000000000000000000"}"#;
+
+    app.send(events::postback("user_id", Payload::from_str(payload)?))
+        .await?;
+
+    assert_eq!(app.texts_to("user_id"), ["/echo rejected, expired: true"]);
+    Ok(())
+}
@@ -0,0 +1,100 @@
+//! The variants of a `#[derive(RussengerPayload)]` enum are routed to their own path and decoded for `Router::payload`.
+#![cfg(feature = "testing")]
+
+use russenger::prelude::*;
+use russenger::testing::TestApp;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, PartialEq, RussengerPayload, Serialize, Deserialize)]
+#[payload(prefix = "/shop")]
+enum Shop {
+    Catalog,
+    ShowProduct {
+        id: u32,
+    },
+    #[payload(path = "/cart")]
+    AddToCart(u32),
+}
+
+#[derive(Debug, PartialEq, RussengerPayload, Serialize, Deserialize)]
+enum Menu {
+    GetStarted,
+}
+
+async fn index(res: Res, req: Req) -> Result<()> {
+    res.send(TextModel::new(&req.user, "Welcome")).await?;
+    res.redirect(Shop::Catalog.path()).await?;
+    Ok(())
+}
+
+async fn shop(res: Res, req: Req, shop: Shop) -> Result<()> {
+    res.send(TextModel::new(
+        &req.user,
+        format!("{shop:?} at {}", req.path),
+    ))
+    .await?;
+    Ok(())
+}
+
+async fn on_error(res: Res, req: Req, path: String, err: error::Error) -> Result<()> {
+    let invalid_data = err.is::<serde_json::Error>();
+    res.send(TextModel::new(
+        &req.user,
+        format!("{path} failed, invalid data: {invalid_data}"),
+    ))
+    .await?;
+    Ok(())
+}
+
+async fn app() -> Result<TestApp> {
+    TestApp::build(|app| {
+        app.on_error(on_error)
+            .attach(Router::new().add("/", index).payload(shop))
+    })
+    .await
+}
+
+#[test]
+fn variants_are_routed_to_their_path() {
+    assert_eq!(
+        Shop::PATHS,
+        ["/shop/catalog", "/shop/show_product", "/cart"]
+    );
+    assert_eq!(Shop::ShowProduct { id: 1 }.path(), "/shop/show_product");
+    assert_eq!(Shop::AddToCart(1).path(), "/cart");
+    assert_eq!(Menu::PATHS, ["/get_started"]);
+    assert_eq!(Menu::GetStarted.path(), "/get_started");
+}
+
+#[tokio::test]
+async fn postbacks_reach_the_action_decoded() -> Result<()> {
+    let app = app().await?;
+
+    app.postback("user_id", Shop::ShowProduct { id: 42 }.into())
+        .await?;
+    app.postback("user_id", Shop::AddToCart(7).into()).await?;
+
+    assert_eq!(
+        app.texts_to("user_id"),
+        [
+            "ShowProduct { id: 42 } at /shop/show_product",
+            "AddToCart(7) at /cart"
+        ]
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn text_messages_on_a_payload_route_fail_to_decode() -> Result<()> {
+    let app = app().await?;
+
+    app.text("user_id", "Hi").await?;
+    app.assert_path("user_id", "/shop/catalog").await;
+    app.text("user_id", "Show me the catalog").await?;
+
+    assert_eq!(
+        app.texts_to("user_id"),
+        ["Welcome", "/shop/catalog failed, invalid data: true"]
+    );
+    Ok(())
+}
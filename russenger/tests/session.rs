@@ -0,0 +1,93 @@
+//! The session keeps values for every user between their actions.
+#![cfg(feature = "testing")]
+
+use std::time::Duration;
+
+use russenger::prelude::*;
+use russenger::testing::TestApp;
+
+/// Adds the text to the cart of the user, or runs the command it names.
+async fn index(res: Res, req: Req) -> Result<()> {
+    let text: String = req.data.get_value()?;
+    let session = req.session();
+    match text.as_str() {
+        "show" => {
+            let cart: Option<Vec<String>> = session.get("cart").await?;
+            res.send(TextModel::new(&req.user, format!("{cart:?}")))
+                .await?;
+        }
+        "empty" => session.remove("cart").await?,
+        "expiring" => {
+            let cart = vec!["expiring".to_owned()];
+            session
+                .set_with_ttl("cart", &cart, Duration::from_secs(3))
+                .await?
+        }
+        item => {
+            let mut cart: Vec<String> = session.get("cart").await?.unwrap_or_default();
+            cart.push(item.to_owned());
+            session.set("cart", &cart).await?;
+        }
+    }
+    Ok(())
+}
+
+async fn app() -> Result<TestApp> {
+    TestApp::new(router![("/", index)]).await
+}
+
+#[tokio::test]
+async fn values_are_kept_for_every_user() -> Result<()> {
+    let app = app().await?;
+
+    app.text("alice", "book").await?;
+    app.text("alice", "pen \"blue\" 'ink'").await?;
+    app.text("bob", "lamp").await?;
+    app.text("alice", "show").await?;
+    app.text("bob", "show").await?;
+
+    assert_eq!(
+        app.texts_to("alice"),
+        [r#"Some(["book", "pen \"blue\" 'ink'"])"#]
+    );
+    assert_eq!(app.texts_to("bob"), [r#"Some(["lamp"])"#]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn removed_values_are_gone() -> Result<()> {
+    let app = app().await?;
+
+    app.text("user_id", "book").await?;
+    app.text("user_id", "empty").await?;
+    app.text("user_id", "show").await?;
+
+    assert_eq!(app.texts_to("user_id"), ["None"]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn values_expire_after_their_ttl() -> Result<()> {
+    let app = app().await?;
+
+    app.text("user_id", "expiring").await?;
+    app.text("user_id", "show").await?;
+    tokio::time::sleep(Duration::from_millis(4100)).await;
+    app.text("user_id", "show").await?;
+
+    assert_eq!(app.texts_to("user_id"), [r#"Some(["expiring"])"#, "None"]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn setting_a_value_again_replaces_its_ttl() -> Result<()> {
+    let app = app().await?;
+
+    app.text("user_id", "expiring").await?;
+    app.text("user_id", "book").await?;
+    tokio::time::sleep(Duration::from_millis(4100)).await;
+    app.text("user_id", "show").await?;
+
+    assert_eq!(app.texts_to("user_id"), [r#"Some(["expiring", "book"])"#]);
+    Ok(())
+}
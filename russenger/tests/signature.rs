@@ -0,0 +1,86 @@
+//! The webhook rejects the events whose `X-Hub-Signature-256` does not match the body.
+#![cfg(feature = "testing")]
+
+use russenger::prelude::*;
+use russenger::testing::{events, TestApp};
+
+const APP_SECRET: &str = "app_secret";
+
+async fn index(res: Res, req: Req) -> Result<()> {
+    res.send(TextModel::new(&req.user, "Hello")).await?;
+    Ok(())
+}
+
+async fn signed_app() -> Result<TestApp> {
+    TestApp::build(|app| app.app_secret(APP_SECRET).attach(router![("/", index)])).await
+}
+
+async fn post(app: &TestApp, body: Vec<u8>, signature: Option<&str>) -> Result<u16> {
+    let mut request = reqwest::Client::new()
+        .post(app.webhook_url())
+        .header("Content-Type", "application/json");
+    if let Some(signature) = signature {
+        request = request.header("X-Hub-Signature-256", signature);
+    }
+    Ok(request.body(body).send().await?.status().as_u16())
+}
+
+#[tokio::test]
+async fn signed_events_reach_the_actions() -> Result<()> {
+    let app = signed_app().await?;
+
+    app.text("user_id", "Hi").await?;
+
+    assert_eq!(app.texts_to("user_id"), ["Hello"]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn unsigned_events_are_forbidden_when_a_secret_is_set() -> Result<()> {
+    let app = signed_app().await?;
+    let body = serde_json::to_vec(&events::text("user_id", "Hi"))?;
+
+    assert_eq!(post(&app, body, None).await?, 403);
+
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    assert!(app.graph().requests().is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn launching_without_an_app_secret_fails() -> Result<()> {
+    let config = AppConfig::new()
+        .page_access_token("page_access_token")
+        .verify_token("verify_token")
+        .database_url("sqlite::memory:");
+    let app = App::with_config(config)
+        .await?
+        .attach(router![("/", index)]);
+
+    let err = app
+        .launch()
+        .await
+        .expect_err("Launched without an App Secret");
+
+    assert_eq!(
+        err.to_string(),
+        "Missing configuration: app_secret is required"
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn forged_events_never_reach_the_queue() -> Result<()> {
+    let app = signed_app().await?;
+    let body = serde_json::to_vec(&events::text("user_id", "Hi"))?;
+    let forged = format!("sha256={}", "0".repeat(64));
+
+    assert_eq!(post(&app, body.clone(), Some(&forged)).await?, 403);
+    assert_eq!(post(&app, body.clone(), None).await?, 403);
+    assert_eq!(post(&app, body, Some("sha256=zz")).await?, 403);
+
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    assert!(app.graph().requests().is_empty());
+    assert_eq!(app.action_path("user_id").await, None);
+    Ok(())
+}
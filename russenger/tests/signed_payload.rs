@@ -0,0 +1,145 @@
+//! With `sign_payloads`, only the payloads signed by the app run their action, the others reach `on_rejected_payload`.
+#![cfg(feature = "testing")]
+
+use std::str::FromStr;
+use std::time::Duration;
+
+use russenger::prelude::*;
+use russenger::testing::{events, TestApp};
+
+const SECRET: &str = "payload_secret";
+
+async fn index(res: Res, req: Req) -> Result<()> {
+    let buy = Payload::new("/buy", Some(Data::new("discount")));
+    let quick_replies = vec![QuickReply::new("Buy", None, buy)];
+    res.send(QuickReplyModel::new(&req.user, "Buy?", quick_replies))
+        .await?;
+    Ok(())
+}
+
+async fn buy(res: Res, req: Req) -> Result<()> {
+    let offer: String = req.data.get_value()?;
+    res.send(TextModel::new(&req.user, format!("Bought with {offer}")))
+        .await?;
+    Ok(())
+}
+
+async fn admin(res: Res, req: Req) -> Result<()> {
+    res.send(TextModel::new(&req.user, "Admin")).await?;
+    Ok(())
+}
+
+async fn on_rejected(res: Res, req: Req, path: String, err: error::Error) -> Result<()> {
+    let reason = match err.downcast_ref::<PayloadError>() {
+        Some(PayloadError::MissingSignature) => "missing signature",
+        Some(PayloadError::InvalidSignature) => "invalid signature",
+        Some(PayloadError::Expired) => "expired",
+        _ => "other",
+    };
+    res.send(TextModel::new(
+        &req.user,
+        format!("{path} rejected: {reason}"),
+    ))
+    .await?;
+    Ok(())
+}
+
+async fn app(on_rejected_payload: bool) -> Result<TestApp> {
+    TestApp::build(|app| {
+        let app = app.sign_payloads(SECRET);
+        let app = match on_rejected_payload {
+            true => app.on_rejected_payload(on_rejected),
+            false => app,
+        };
+        app.attach(router![("/", index), ("/buy", buy), ("/admin", admin)])
+    })
+    .await
+}
+
+/// Returns the JSON of the payload of the quick reply sent by `index` to `user`.
+async fn sent_payload(app: &TestApp, user: &str) -> Result<String> {
+    app.text(user, "Hi").await?;
+    let sent = app.sent_to(user);
+    let payload = sent[0]["message"]["quick_replies"][0]["payload"]
+        .as_str()
+        .expect("No quick reply sent")
+        .to_owned();
+    app.clear();
+    Ok(payload)
+}
+
+async fn tap(app: &TestApp, user: &str, payload: &str) -> Result<()> {
+    app.send(events::quick_reply(
+        user,
+        "Buy",
+        Payload::from_str(payload)?,
+    ))
+    .await
+}
+
+#[tokio::test]
+async fn signed_payloads_run_their_action() -> Result<()> {
+    let app = app(true).await?;
+    let payload = sent_payload(&app, "user_id").await?;
+    assert!(payload.contains("signature"), "{payload}");
+
+    tap(&app, "user_id", &payload).await?;
+
+    app.assert_text("user_id", "Bought with discount");
+    Ok(())
+}
+
+#[tokio::test]
+async fn tampered_payloads_are_rejected() -> Result<()> {
+    let app = app(true).await?;
+    let payload = sent_payload(&app, "user_id").await?;
+
+    tap(&app, "user_id", &payload.replace("/buy", "/admin")).await?;
+    tap(&app, "user_id", &payload.replace("discount", "free")).await?;
+
+    assert_eq!(
+        app.texts_to("user_id"),
+        [
+            "/admin rejected: invalid signature",
+            "/buy rejected: invalid signature"
+        ]
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn unsigned_payloads_are_rejected() -> Result<()> {
+    let app = app(true).await?;
+
+    app.send(events::postback("user_id", Payload::new("/admin", None)))
+        .await?;
+
+    assert_eq!(
+        app.texts_to("user_id"),
+        ["/admin rejected: missing signature"]
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn expired_payloads_are_rejected() -> Result<()> {
+    let app = app(true).await?;
+    let payload = Payload::new("/buy", Some(Data::new("discount"))).expires_in(Duration::ZERO);
+
+    app.postback("user_id", payload).await?;
+
+    assert_eq!(app.texts_to("user_id"), ["/buy rejected: expired"]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn rejected_payloads_without_a_handler_run_nothing() -> Result<()> {
+    let app = app(false).await?;
+
+    app.send(events::postback("user_id", Payload::new("/admin", None)))
+        .await?;
+
+    assert!(app.sent_to("user_id").is_empty());
+    app.assert_path("user_id", "/").await;
+    Ok(())
+}
@@ -0,0 +1,226 @@
+//! Drives the routes of an application through `TestApp`, and checks what `MockGraph` received.
+#![cfg(feature = "testing")]
+
+use russenger::prelude::*;
+use russenger::testing::{events, MockGraph, TestApp};
+use serde_json::json;
+
+async fn index(res: Res, req: Req) -> Result<()> {
+    res.send(TextModel::new(&req.user, "What is your name?"))
+        .await?;
+    res.redirect("/name").await?;
+    Ok(())
+}
+
+async fn name(res: Res, req: Req) -> Result<()> {
+    let name: String = req.data.get_value()?;
+    res.send(TextModel::new(&req.user, format!("Hello {name}")))
+        .await?;
+    res.redirect("/").await?;
+    Ok(())
+}
+
+async fn product(res: Res, req: Req) -> Result<()> {
+    let id: u32 = req.data.get_value()?;
+    res.send(TextModel::new(&req.user, format!("Product {id}")))
+        .await?;
+    Ok(())
+}
+
+async fn app() -> Result<TestApp> {
+    TestApp::new(router![
+        ("/", index),
+        ("/name", name),
+        ("/product", product)
+    ])
+    .await
+}
+
+fn texts(graph: &MockGraph, user: &str) -> Vec<String> {
+    graph
+        .requests_to(user)
+        .iter()
+        .map(|request| {
+            request.body["message"]["text"]
+                .as_str()
+                .unwrap_or_default()
+                .to_owned()
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn text_messages_run_the_action_of_the_path_of_the_user() -> Result<()> {
+    let app = app().await?;
+
+    app.text("user_id", "Hi").await?;
+
+    let requests = app.graph().requests_to("user_id");
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].endpoint, "messages");
+    assert_eq!(
+        requests[0].access_token.as_deref(),
+        Some("test_page_access_token")
+    );
+    assert_eq!(
+        requests[0].body,
+        json!({
+            "recipient": { "id": "user_id" },
+            "messaging_type": "RESPONSE",
+            "message": { "text": "What is your name?" },
+        })
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn redirects_move_the_user_to_the_next_action() -> Result<()> {
+    let app = app().await?;
+
+    app.text("user_id", "Hi").await?;
+    app.assert_path("user_id", "/name").await;
+
+    app.text("user_id", "Joe").await?;
+    app.assert_path("user_id", "/").await;
+    assert_eq!(
+        texts(app.graph(), "user_id"),
+        ["What is your name?", "Hello Joe"]
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn postbacks_run_the_action_of_their_payload() -> Result<()> {
+    let app = app().await?;
+
+    app.postback("user_id", Payload::new("/product", Some(Data::new(42))))
+        .await?;
+    app.quick_reply("user_id", "7", Payload::new("/product", Some(Data::new(7))))
+        .await?;
+
+    assert_eq!(texts(app.graph(), "user_id"), ["Product 42", "Product 7"]);
+    app.assert_path("user_id", "/").await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn users_are_answered_separately() -> Result<()> {
+    let app = app().await?;
+
+    app.text("alice", "Hi").await?;
+    app.text("bob", "Hi").await?;
+    app.text("alice", "Alice").await?;
+
+    assert_eq!(
+        texts(app.graph(), "alice"),
+        ["What is your name?", "Hello Alice"]
+    );
+    assert_eq!(texts(app.graph(), "bob"), ["What is your name?"]);
+    app.assert_path("alice", "/").await;
+    app.assert_path("bob", "/name").await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn graph_failures_reach_the_error_handler() -> Result<()> {
+    let app = TestApp::build(|app| {
+        app.attach(router![("/", index)]).on_error(
+            |res: Res, req: Req, path: String, err: error::Error| async move {
+                let recipient_unavailable = matches!(
+                    err.downcast_ref::<GraphError>(),
+                    Some(GraphError::RecipientUnavailable(_))
+                );
+                res.send(TextModel::new(
+                    &req.user,
+                    format!("{path} failed, recipient unavailable: {recipient_unavailable}"),
+                ))
+                .await?;
+                Ok(())
+            },
+        )
+    })
+    .await?;
+    app.graph().fail_next(
+        400,
+        json!({ "error": { "message": "No matching user found", "code": 100, "error_subcode": 2018001 } }),
+    );
+
+    app.text("user_id", "Hi").await?;
+
+    assert_eq!(
+        texts(app.graph(), "user_id"),
+        [
+            "What is your name?",
+            "/ failed, recipient unavailable: true"
+        ]
+    );
+    app.assert_path("user_id", "/").await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn users_of_each_page_have_their_own_path() -> Result<()> {
+    let app = TestApp::build(|app| {
+        app.page("shop_page_id", "shop_page_access_token")
+            .attach(router![("/", index), ("/name", name)])
+    })
+    .await?;
+
+    app.send(events::on_page(
+        "shop_page_id",
+        events::text("user_id", "Hi"),
+    ))
+    .await?;
+
+    app.assert_path_on("shop_page_id", "user_id", "/name").await;
+    assert_eq!(app.action_path("user_id").await, None);
+    let requests = app.graph().requests_to("user_id");
+    assert_eq!(
+        requests[0].access_token.as_deref(),
+        Some("shop_page_access_token")
+    );
+
+    app.text("user_id", "Hi").await?;
+    app.assert_path("user_id", "/name").await;
+    app.send(events::on_page(
+        "shop_page_id",
+        events::text("user_id", "Joe"),
+    ))
+    .await?;
+    app.assert_path_on("shop_page_id", "user_id", "/").await;
+    app.assert_path("user_id", "/name").await;
+    Ok(())
+}
+
+struct Unserializable;
+
+impl serde::Serialize for Unserializable {
+    fn serialize<S: serde::Serializer>(&self, _: S) -> std::result::Result<S::Ok, S::Error> {
+        Err(serde::ser::Error::custom("not serializable"))
+    }
+}
+
+impl ResponseModel for Unserializable {
+    const END_POINT: &'static str = "me/messages";
+}
+
+async fn send_unserializable(res: Res, req: Req) -> Result<()> {
+    let err = res.send(Unserializable).await.unwrap_err();
+    assert!(matches!(err, GraphError::Serialize(_)), "{err:?}");
+    res.send(TextModel::new(&req.user, err.to_string())).await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn models_that_fail_to_serialize_are_not_sent() -> Result<()> {
+    let app = TestApp::build(|app| app.attach(router![("/", send_unserializable)])).await?;
+
+    app.text("user_id", "Hi").await?;
+
+    assert_eq!(
+        texts(app.graph(), "user_id"),
+        ["invalid response model: not serializable"]
+    );
+    assert_eq!(app.graph().requests().len(), 1);
+    Ok(())
+}
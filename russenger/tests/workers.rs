@@ -0,0 +1,84 @@
+//! The workers run the actions of different users in parallel, and stop once the running actions have finished.
+#![cfg(feature = "testing")]
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use russenger::prelude::*;
+use russenger::testing::{events, TestApp};
+use tokio::sync::Barrier;
+
+async fn index(res: Res, req: Req) -> Result<()> {
+    res.send(TextModel::new(&req.user, "Hello")).await?;
+    Ok(())
+}
+
+async fn slow(res: Res, req: Req) -> Result<()> {
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    let text: String = req.data.get_value()?;
+    res.send(TextModel::new(&req.user, format!("Done {text}")))
+        .await?;
+    Ok(())
+}
+
+async fn post(app: &TestApp, data: impl serde::Serialize) -> Result<u16> {
+    let response = reqwest::Client::new()
+        .post(app.webhook_url())
+        .json(&data)
+        .send()
+        .await?;
+    Ok(response.status().as_u16())
+}
+
+#[tokio::test]
+async fn events_posted_after_the_shutdown_are_refused() -> Result<()> {
+    let mut app = TestApp::new(router![("/", index)]).await?;
+    app.shutdown().await;
+
+    assert_eq!(post(&app, events::text("user_id", "Hi")).await?, 503);
+    assert_eq!(post(&app, events::text("user_id", "Hi again")).await?, 503);
+
+    assert!(app.sent_to("user_id").is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn actions_of_different_users_run_in_parallel() -> Result<()> {
+    // Each action waits for the action of the other user, which only returns if both run at the same time.
+    let barrier = Arc::new(Barrier::new(2));
+    let app = TestApp::new(router![("/", move |res: Res, req: Req| {
+        let barrier = barrier.clone();
+        async move {
+            let together = tokio::time::timeout(Duration::from_secs(2), barrier.wait())
+                .await
+                .is_ok();
+            res.send(TextModel::new(&req.user, format!("Together: {together}")))
+                .await?;
+            Ok(())
+        }
+    })])
+    .await?;
+
+    app.send(events::batch([
+        events::text("alice", "Hi"),
+        events::text("bob", "Hi"),
+    ]))
+    .await?;
+
+    assert_eq!(app.texts_to("alice"), ["Together: true"]);
+    assert_eq!(app.texts_to("bob"), ["Together: true"]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn shutdown_waits_for_the_running_and_the_queued_actions() -> Result<()> {
+    let mut app = TestApp::new(router![("/", slow)]).await?;
+
+    assert_eq!(post(&app, events::text("user_id", "1")).await?, 200);
+    assert_eq!(post(&app, events::text("user_id", "2")).await?, 200);
+    assert!(app.sent_to("user_id").is_empty());
+    app.shutdown().await;
+
+    assert_eq!(app.texts_to("user_id"), ["Done 1", "Done 2"]);
+    Ok(())
+}
@@ -0,0 +1,53 @@
+//! The expansion of `#[action]`.
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::{Error, FnArg, Ident, ItemFn, Result};
+
+pub(crate) fn expand(function: ItemFn) -> Result<TokenStream> {
+    let ItemFn {
+        attrs,
+        vis,
+        sig,
+        block,
+    } = function;
+    if sig.asyncness.is_none() {
+        return Err(Error::new_spanned(
+            sig.fn_token,
+            "an action must be `async`",
+        ));
+    }
+    if !sig.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &sig.generics,
+            "an action can't be generic",
+        ));
+    }
+
+    let res = Ident::new("res", Span::mixed_site());
+    let req = Ident::new("req", Span::mixed_site());
+    let mut extractions = Vec::new();
+    for input in &sig.inputs {
+        let FnArg::Typed(argument) = input else {
+            return Err(Error::new_spanned(input, "an action can't take `self`"));
+        };
+        let (pat, ty) = (&argument.pat, &argument.ty);
+        extractions.push(quote! {
+            let #pat: #ty =
+                <#ty as ::russenger::core::extract::FromRequest>::from_request(&#res, &#req).await?;
+        });
+    }
+
+    let name = &sig.ident;
+    let output = &sig.output;
+    let statements = &block.stmts;
+    Ok(quote! {
+        #(#attrs)*
+        #vis async fn #name(
+            #res: ::russenger::core::response::Res,
+            #req: ::russenger::core::request::Req,
+        ) #output {
+            #(#extractions)*
+            #(#statements)*
+        }
+    })
+}
@@ -0,0 +1,72 @@
+//! Procedural macros of the [russenger](https://docs.rs/russenger) library.
+//!
+//! The macros are re-exported by `russenger`, use them through `russenger::prelude`.
+use proc_macro::TokenStream;
+use syn::{parse_macro_input, DeriveInput, ItemFn};
+
+mod action;
+mod payload;
+
+/// Turns an async function whose arguments implement `FromRequest` into an action taking `Res` and `Req`.
+///
+/// The arguments are extracted in order before the body runs. If an extraction fails, the error is returned by the
+/// action and the body does not run.
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// use russenger::prelude::*;
+///
+/// #[action]
+/// async fn name(res: Res, req: Req, Text(name): Text) -> Result<()> {
+///     res.send(TextModel::new(&req.user, format!("Hello {name}"))).await?;
+///     Ok(())
+/// }
+///
+/// let router = router![("/name", name)];
+/// ```
+#[proc_macro_attribute]
+pub fn action(args: TokenStream, input: TokenStream) -> TokenStream {
+    if !args.is_empty() {
+        let args = proc_macro2::TokenStream::from(args);
+        return syn::Error::new_spanned(args, "`action` takes no arguments")
+            .into_compile_error()
+            .into();
+    }
+    let function = parse_macro_input!(input as ItemFn);
+    action::expand(function)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Derives `RussengerPayload` for an enum, so that its variants can be sent as payloads and handled with
+/// `Router::payload`.
+///
+/// Every variant is routed to the path `/{variant}`, where `variant` is the name of the variant in snake case. The
+/// path of a variant can be set with `#[payload(path = "/...")]`, and `#[payload(prefix = "/...")]` on the enum
+/// prefixes the paths of every variant.
+///
+/// The enum must also implement `Serialize` and `Deserialize`.
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// use russenger::prelude::*;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(RussengerPayload, Serialize, Deserialize)]
+/// #[payload(prefix = "/shop")]
+/// enum Shop {
+///     Catalog,
+///     Product { id: u32 },
+///     #[payload(path = "/shop/cart")]
+///     AddToCart(u32),
+/// }
+/// ```
+#[proc_macro_derive(RussengerPayload, attributes(payload))]
+pub fn derive_russenger_payload(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    payload::expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
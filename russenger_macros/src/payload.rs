@@ -0,0 +1,162 @@
+//! The expansion of `#[derive(RussengerPayload)]`.
+use std::collections::HashMap;
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Attribute, Data, DeriveInput, Error, Fields, LitStr, Result};
+
+pub(crate) fn expand(input: DeriveInput) -> Result<TokenStream> {
+    let Data::Enum(data) = &input.data else {
+        return Err(Error::new_spanned(
+            &input.ident,
+            "RussengerPayload can only be derived for enums",
+        ));
+    };
+    let prefix = attribute(&input.attrs, "prefix")?
+        .map(|prefix| prefix.value())
+        .unwrap_or_default();
+
+    let mut paths = Vec::new();
+    let mut arms = Vec::new();
+    let mut seen = HashMap::new();
+    for variant in &data.variants {
+        let path = match attribute(&variant.attrs, "path")? {
+            Some(path) => path.value(),
+            None => format!("{prefix}/{}", snake_case(&variant.ident.to_string())),
+        };
+        if !path.starts_with('/') {
+            return Err(Error::new_spanned(variant, "the path must start with `/`"));
+        }
+        if let Some(other) = seen.insert(path.clone(), variant.ident.to_string()) {
+            let message = format!("the path `{path}` is already used by `{other}`");
+            return Err(Error::new_spanned(variant, message));
+        }
+
+        let ident = &variant.ident;
+        let pattern = match &variant.fields {
+            Fields::Named(_) => quote! { Self::#ident { .. } },
+            Fields::Unnamed(_) => quote! { Self::#ident(..) },
+            Fields::Unit => quote! { Self::#ident },
+        };
+        arms.push(quote! { #pattern => #path });
+        paths.push(path);
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let body = if arms.is_empty() {
+        quote! { match *self {} }
+    } else {
+        quote! { match self { #(#arms,)* } }
+    };
+    Ok(quote! {
+        impl #impl_generics ::russenger::response_models::payload::RussengerPayload
+            for #name #ty_generics #where_clause
+        {
+            const PATHS: &'static [&'static str] = &[#(#paths),*];
+
+            fn path(&self) -> &'static str {
+                #body
+            }
+        }
+
+        impl #impl_generics ::core::convert::From<#name #ty_generics>
+            for ::russenger::response_models::payload::Payload #where_clause
+        {
+            fn from(payload: #name #ty_generics) -> Self {
+                ::russenger::response_models::payload::RussengerPayload::to_payload(&payload)
+            }
+        }
+    })
+}
+
+/// Returns the value of `#[payload(name = "...")]` among `attrs`.
+fn attribute(attrs: &[Attribute], name: &str) -> Result<Option<LitStr>> {
+    let mut value = None;
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("payload")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident(name) {
+                value = Some(meta.value()?.parse()?);
+                Ok(())
+            } else {
+                Err(meta.error("unsupported payload attribute"))
+            }
+        })?;
+    }
+    Ok(value)
+}
+
+fn snake_case(ident: &str) -> String {
+    let mut snake = String::new();
+    for (index, char) in ident.chars().enumerate() {
+        if char.is_uppercase() {
+            if index > 0 {
+                snake.push('_');
+            }
+            snake.extend(char.to_lowercase());
+        } else {
+            snake.push(char);
+        }
+    }
+    snake
+}
+
+#[cfg(test)]
+mod tests {
+    use syn::parse_quote;
+
+    use super::*;
+
+    fn error(input: DeriveInput) -> String {
+        expand(input).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn variants_are_named_in_snake_case() {
+        assert_eq!(snake_case("Catalog"), "catalog");
+        assert_eq!(snake_case("AddToCart"), "add_to_cart");
+        assert_eq!(snake_case("Step2Done"), "step2_done");
+    }
+
+    #[test]
+    fn paths_must_start_with_a_slash() {
+        let input = parse_quote! {
+            enum Menu {
+                #[payload(path = "help")]
+                Help,
+            }
+        };
+        assert_eq!(error(input), "the path must start with `/`");
+    }
+
+    #[test]
+    fn paths_must_be_unique() {
+        let input = parse_quote! {
+            enum Menu {
+                Help,
+                #[payload(path = "/help")]
+                Support,
+            }
+        };
+        assert_eq!(error(input), "the path `/help` is already used by `Help`");
+    }
+
+    #[test]
+    fn only_enums_and_known_attributes_are_supported() {
+        let input = parse_quote! {
+            struct Menu;
+        };
+        assert_eq!(
+            error(input),
+            "RussengerPayload can only be derived for enums"
+        );
+
+        let input = parse_quote! {
+            #[payload(route = "/menu")]
+            enum Menu {
+                Help,
+            }
+        };
+        assert_eq!(error(input), "unsupported payload attribute");
+    }
+}